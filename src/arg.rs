@@ -1,89 +1,168 @@
-use std::str::FromStr;
+use std::{fmt::Display, str::FromStr};
 
 use crate::{
-    cli_error::{CliError, CliResult},
-    input::{Completor, Input, InputType},
+    cli_error::{CliError, CliResult, ErrorKind},
+    input::{self, Completor, DefaultValue, Input, InputType, Parser, Validator},
+    value_enum::{self, PossibleValue, ValueEnum},
 };
 
-#[derive(Default)]
-pub struct Arg<'a, T: FromStr + Clone> {
+pub struct Arg<'a, T: Clone> {
     pub name: String,
     pub description: Option<String>,
     pub value: Option<T>,
     pub completor: Option<Completor<'a>>,
-    pub default_value: Option<T>,
+    pub env: Option<String>,
+    pub default_value: Option<DefaultValue<'a, T>>,
+    pub parser: Parser<'a, T>,
+    pub validators: Vec<Validator<'a, T>>,
+    pub possible_values: Vec<PossibleValue>,
 }
 
 impl<'a, T> Arg<'a, T>
 where
-    T: FromStr + Clone,
+    T: FromStr + Clone + 'a,
 {
     pub fn name(name: &str) -> Self {
+        let arg = name.to_string();
+        Self::with_parser(name, move |value| {
+            value.parse().map_err(|_| CliError::unparsable(value, &arg))
+        })
+    }
+}
+
+impl<'a, T> Arg<'a, T>
+where
+    T: FromStr + Clone + 'a,
+    T::Err: Display + 'static,
+{
+    value_builders!(parse_details);
+}
+
+/// an unnamed arg parsed with `FromStr`, for filling in fields with `..Default::default()`
+impl<'a, T> Default for Arg<'a, T>
+where
+    T: FromStr + Clone + 'a,
+{
+    fn default() -> Self {
+        Self::name("")
+    }
+}
+
+impl<'a, T: ValueEnum + 'a> Arg<'a, T> {
+    /// one of `T`'s values, which completions offer and help lists
+    pub fn value_enum(name: &str) -> Self {
+        let arg = name.to_string();
+        Self {
+            possible_values: T::possible_values(),
+            ..Self::with_parser(name, move |value| value_enum::parse(value, &arg))
+        }
+    }
+}
+
+impl<'a, T: Clone> Arg<'a, T> {
+    /// like `name`, for types parsed by `parser` rather than `FromStr`
+    pub fn with_parser<F>(name: &str, parser: F) -> Self
+    where
+        F: FnMut(&str) -> CliResult<T> + 'a,
+    {
         Self {
             name: name.to_string(),
             description: None,
             value: None,
             completor: None,
             default_value: None,
+            env: None,
+            parser: Box::new(parser),
+            validators: vec![],
+            possible_values: vec![],
         }
     }
 
-    pub fn default(mut self, default: T) -> Self {
-        self.default_value = Some(default);
-        self
-    }
-
     pub fn description(mut self, description: &str) -> Self {
         self.description = Some(description.to_string());
         self
     }
 
+    /// the parsed value, or the default. Parsing fails before the handler runs if there's neither,
+    /// so this only panics after `take`
     pub fn get(&self) -> T {
+        self.try_get()
+            .unwrap_or_else(|| panic!("{} was read without a value or a default", self.name))
+    }
+
+    pub fn try_get(&self) -> Option<T> {
         self.value
             .clone()
-            .unwrap_or_else(|| self.default_value.clone().unwrap())
+            .or_else(|| self.default_value.as_ref().map(DefaultValue::get))
     }
 
-    pub fn completor<F>(mut self, completor: F) -> Self
-    where
-        F: FnMut(&str) -> CliResult<Vec<String>> + 'a,
-    {
-        self.completor = Some(Box::new(completor));
-        self
+    /// moves the parsed value out rather than cloning it, later reads see the default
+    pub fn take(&mut self) -> T {
+        self.value
+            .take()
+            .or_else(|| self.default_value.as_ref().map(DefaultValue::get))
+            .unwrap_or_else(|| panic!("{} was taken without a value or a default", self.name))
     }
+
+    value_builders!();
 }
 
 impl<'a> Arg<'a, String> {
     pub fn str(name: &str) -> Self {
-        Self::name(name)
+        Self::name(name).parse_details()
     }
 }
 
 impl<'a> Arg<'a, i32> {
     pub fn i32(name: &str) -> Self {
-        Self::name(name)
+        Self::name(name).parse_details()
     }
 }
 
-impl<'a, T: FromStr + Clone> Input for Arg<'a, T> {
+impl<'a, T: Clone> Input for Arg<'a, T> {
     fn parse(&mut self, token: &str) -> CliResult<bool> {
-        if token.len() > 2 && &token[0..1] == "-" && &token[0..2] == "--" {
-            return Err(CliError::from(format!(
-                "unexpected flag \"{}\" found while looking for argument \"{}\"",
-                token, self.name
-            )));
+        if token.len() > 2 && token.starts_with("--") {
+            return Err(CliError::new(
+                ErrorKind::UnknownFlag,
+                format!(
+                    "unexpected flag \"{}\" found while looking for argument \"{}\"",
+                    token, self.name
+                ),
+            )
+            .token(token)
+            .input(&self.name));
         }
-        self.value = Some(token.parse().map_err(|_| {
-            CliError::from(format!("{} cannot be parsed for {}.", token, self.name))
-        })?);
+        self.parse_value(token)?;
 
         Ok(true)
     }
 
+    fn expects_value(&self, _token: &str) -> bool {
+        false
+    }
+
+    fn parse_value(&mut self, value: &str) -> CliResult<()> {
+        let parsed = (self.parser)(value).map_err(|err| err.invalid_value(value, &self.name))?;
+        for validator in &self.validators {
+            validator.check(&parsed, value, &self.name)?;
+        }
+        self.value = Some(parsed);
+
+        Ok(())
+    }
+
+    fn parses(&mut self, value: &str) -> bool {
+        (self.parser)(value).is_ok()
+    }
+
     fn display_name(&self) -> String {
         self.name.clone()
     }
 
+    fn short_name(&self) -> Option<char> {
+        None
+    }
+
     fn type_name(&self) -> InputType {
         InputType::Arg
     }
@@ -96,14 +175,22 @@ impl<'a, T: FromStr + Clone> Input for Arg<'a, T> {
         if let Some(completor) = &mut self.completor {
             completor(value)
         } else {
-            Ok(vec![])
+            Ok(value_enum::complete(&self.possible_values, value))
         }
     }
 
+    fn possible_values(&self) -> Vec<PossibleValue> {
+        self.possible_values.clone()
+    }
+
     fn is_bool_flag(&self) -> bool {
         false
     }
 
+    fn env_var(&self) -> Option<String> {
+        self.env.clone()
+    }
+
     fn description(&self) -> Option<String> {
         self.description.clone()
     }
@@ -111,4 +198,12 @@ impl<'a, T: FromStr + Clone> Input for Arg<'a, T> {
     fn has_default(&self) -> bool {
         self.default_value.is_some()
     }
+
+    fn default_help(&self) -> Option<String> {
+        self.default_value.as_ref().and_then(DefaultValue::help)
+    }
+
+    fn constraints(&self) -> Option<String> {
+        input::describe(&self.validators)
+    }
 }
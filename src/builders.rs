@@ -0,0 +1,137 @@
+/// the builder methods every kind of flag shares, expanded inside each flag's `impl` block so they
+/// stay inherent methods. Expects `description`, `short` and `hidden` fields.
+macro_rules! flag_builders {
+    () => {
+        pub fn description(mut self, description: &str) -> Self {
+            self.description = Some(description.to_string());
+            self
+        }
+
+        /// answer to `-{short}`, rather than the first letter of the name
+        pub fn short(mut self, short: char) -> Self {
+            self.short = Some(short);
+            self
+        }
+
+        /// only answer to the long form of this flag
+        pub fn no_short(mut self) -> Self {
+            self.short = None;
+            self
+        }
+
+        /// keeps this flag out of help and completions, it's still parsed
+        pub fn hidden(mut self) -> Self {
+            self.hidden = true;
+            self
+        }
+    };
+}
+
+/// the builder methods `Arg` and `Flag` share for parsing, defaulting and validating their value,
+/// expanded like `flag_builders`. Expects `parser`, `default_value`, `validators`, `env` and
+/// `completor` fields. `parse_details` goes in an `impl` block where `T::Err: Display + 'static`.
+macro_rules! value_builders {
+    () => {
+        /// parses values with `parser` rather than `FromStr`, its errors are shown to the user as
+        /// is: a target that's either a uuid or a path looked up in local state
+        pub fn parser<F>(mut self, parser: F) -> Self
+        where
+            F: FnMut(&str) -> $crate::cli_error::CliResult<T> + 'a,
+        {
+            self.parser = Box::new(parser);
+            self
+        }
+
+        /// the value used when the input isn't given, help only shows it after `.show_default()`
+        pub fn default(mut self, default: T) -> Self
+        where
+            T: 'a,
+        {
+            self.default_value = Some($crate::input::DefaultValue::hidden(move || default.clone()));
+            self
+        }
+
+        /// like `default`, but computed when it's needed, `$EDITOR` or `vi`
+        pub fn default_with<F>(mut self, default: F) -> Self
+        where
+            F: Fn() -> T + 'a,
+        {
+            self.default_value = Some($crate::input::DefaultValue::hidden(default));
+            self
+        }
+
+        /// lists the default in help, `[default: vi]`
+        pub fn show_default(mut self) -> Self
+        where
+            T: ::std::fmt::Display,
+        {
+            self.default_value = self.default_value.map($crate::input::DefaultValue::shown);
+            self
+        }
+
+        /// rejects values outside `range` once they're parsed: `.range(1..=65535)`
+        pub fn range<R>(self, range: R) -> Self
+        where
+            T: PartialOrd + 'a,
+            R: ::std::ops::RangeBounds<T> + ::std::fmt::Debug + 'a,
+        {
+            self.validator($crate::input::Validator::range(range))
+        }
+
+        /// rejects values that don't match `pattern`, a small subset of regular expressions
+        pub fn pattern(self, pattern: &str) -> Self
+        where
+            T: AsRef<str> + 'a,
+        {
+            self.validator($crate::input::Validator::pattern(pattern))
+        }
+
+        /// rejects paths to files or folders that don't exist
+        pub fn path_exists(self) -> Self
+        where
+            T: AsRef<::std::path::Path> + 'a,
+        {
+            self.validator($crate::input::Validator::path_exists())
+        }
+
+        /// rejects values `check` returns false for, `description` is what errors and help show
+        pub fn validate<F>(self, description: &str, check: F) -> Self
+        where
+            F: Fn(&T) -> bool + 'a,
+        {
+            self.validator($crate::input::Validator::new(description, check))
+        }
+
+        pub fn validator(mut self, validator: $crate::input::Validator<'a, T>) -> Self {
+            self.validators.push(validator);
+            self
+        }
+
+        /// read the value from `var` when it isn't given on the command line
+        pub fn env(mut self, var: &str) -> Self {
+            self.env = Some(var.to_string());
+            self
+        }
+
+        pub fn completor<F>(mut self, completor: F) -> Self
+        where
+            F: FnMut(&str) -> $crate::cli_error::CliResult<Vec<String>> + 'a,
+        {
+            self.completor = Some(Box::new(completor));
+            self
+        }
+    };
+
+    (parse_details) => {
+        /// adds the reason `FromStr` gave to parse errors and keeps it as their source, `abc
+        /// cannot be parsed for port: invalid digit found in string`
+        pub fn parse_details(self) -> Self {
+            let name = self.name.clone();
+            self.parser(move |value| {
+                value.parse().map_err(|err| {
+                    $crate::cli_error::CliError::unparsable_because(value, &name, err)
+                })
+            })
+        }
+    };
+}
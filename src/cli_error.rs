@@ -1,17 +1,183 @@
-use std::fmt::Display;
+use std::{
+    any::Any,
+    fmt::{Debug, Display},
+};
 
 pub type CliResult<T> = Result<T, CliError>;
 
+/// what went wrong, so callers can react to a failure without matching on its message
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ErrorKind {
+    /// a flag-like token that no flag answers to
+    UnknownFlag,
+    /// a flag that takes a value was given without one
+    MissingValue,
+    /// a required input was never given, or one that another input requires
+    MissingArgument,
+    /// two inputs were given that can't be used together
+    ArgumentConflict,
+    /// a value that couldn't be parsed for its input
+    InvalidValue,
+    /// a positional token left over once every argument was filled
+    UnexpectedArgument,
+    /// a token where a subcommand was expected that names none of them
+    UnknownSubcommand,
+    /// a command with subcommands was invoked without one
+    MissingSubcommand,
+    /// a shortened subcommand or flag name that starts more than one of them
+    AmbiguousPrefix,
+    /// `--help` was given, the message is the help text
+    HelpRequested,
+    /// `--version` was given, the message is the version line
+    VersionRequested,
+    /// returned by a handler, or converted from any other `Display` value
+    Handler,
+}
+
+/// CliError can't implement Display itself because of the blanket `From<D: Display>`, print `msg`
 #[derive(Debug, PartialEq)]
 pub struct CliError {
+    pub kind: ErrorKind,
     pub msg: String,
     pub status: i32,
+    /// the command line token that caused the error
+    pub token: Option<String>,
+    /// the name of the input the error is about
+    pub input: Option<String>,
+    /// the path of the command being parsed, `lockbook edit`
+    pub cmd_path: Option<String>,
+    /// the underlying error, such as the one `FromStr` gave for a value
+    pub source: Option<ErrorSource>,
+}
+
+/// the error a value's parser gave, kept as the error itself so it can be downcast. Boxed to keep
+/// `CliResult` small.
+pub struct ErrorSource(Box<dyn Reason>);
+
+trait Reason: Display {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<E: Display + 'static> Reason for E {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl ErrorSource {
+    pub fn new<E: Display + 'static>(source: E) -> Self {
+        Self(Box::new(source))
+    }
+
+    /// the original error if it's an `E`, `ParseIntError` for a value that isn't a number
+    pub fn downcast_ref<E: 'static>(&self) -> Option<&E> {
+        // through the box, which is itself a `Reason`
+        (*self.0).as_any().downcast_ref()
+    }
+}
+
+impl Display for ErrorSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Debug for ErrorSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ErrorSource")
+            .field(&self.0.to_string())
+            .finish()
+    }
+}
+
+/// sources are compared by their message, the errors themselves rarely implement `PartialEq`
+impl PartialEq for ErrorSource {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_string() == other.to_string()
+    }
+}
+
+impl CliError {
+    pub fn new(kind: ErrorKind, msg: impl Display) -> Self {
+        let status = match kind {
+            ErrorKind::HelpRequested | ErrorKind::VersionRequested => 0,
+            _ => 1,
+        };
+
+        Self {
+            kind,
+            msg: msg.to_string(),
+            status,
+            token: None,
+            input: None,
+            cmd_path: None,
+            source: None,
+        }
+    }
+
+    /// `value` rejected by `FromStr` for `input`, when there's no reason that can be shown
+    pub(crate) fn unparsable(value: &str, input: &str) -> Self {
+        Self::new(
+            ErrorKind::InvalidValue,
+            format!("{value} cannot be parsed for {input}"),
+        )
+        .token(value)
+        .input(input)
+    }
+
+    /// like `unparsable`, with the reason `source` gave attached and kept as the source
+    pub(crate) fn unparsable_because(
+        value: &str,
+        input: &str,
+        source: impl Display + 'static,
+    ) -> Self {
+        Self::unparsable_with(value, input, ErrorSource::new(source))
+    }
+
+    pub(crate) fn unparsable_with(value: &str, input: &str, source: ErrorSource) -> Self {
+        let mut err = Self::new(
+            ErrorKind::InvalidValue,
+            format!("{value} cannot be parsed for {input}: {source}"),
+        )
+        .token(value)
+        .input(input);
+
+        err.source = Some(source);
+        err
+    }
+
+    pub fn token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    pub fn input(mut self, input: &str) -> Self {
+        self.input = Some(input.to_string());
+        self
+    }
+
+    /// an error from parsing `token` for `input`, the parser's own message is kept as is
+    pub(crate) fn invalid_value(mut self, token: &str, input: &str) -> Self {
+        if self.kind == ErrorKind::Handler {
+            self.kind = ErrorKind::InvalidValue;
+        }
+        self.token.get_or_insert_with(|| token.to_string());
+        self.input.get_or_insert_with(|| input.to_string());
+        self
+    }
+
+    /// records the command the error came up in, unless a subcommand already did
+    pub(crate) fn in_cmd(mut self, cmd_path: &str) -> Self {
+        if self.cmd_path.is_none() {
+            self.cmd_path = Some(cmd_path.to_string());
+        }
+        self
+    }
 }
 
 impl<D: Display> From<D> for CliError {
     fn from(value: D) -> Self {
-        let msg = value.to_string();
-        Self { msg, status: 1 }
+        Self::new(ErrorKind::Handler, value)
     }
 }
 
@@ -23,6 +189,15 @@ pub trait Exit {
     fn exit_silently(self);
 }
 
+/// help and version aren't failures, so they go to stdout
+fn print_err(err: &CliError) {
+    if err.status == 0 {
+        println!("{}", err.msg);
+    } else {
+        eprintln!("{}", err.msg);
+    }
+}
+
 impl<O> Exit for CliResult<O> {
     type O = O;
 
@@ -30,7 +205,7 @@ impl<O> Exit for CliResult<O> {
         match self {
             Ok(_) => std::process::exit(0),
             Err(err) => {
-                eprintln!("{}", err.msg);
+                print_err(&err);
                 std::process::exit(err.status);
             }
         }
@@ -40,7 +215,7 @@ impl<O> Exit for CliResult<O> {
         match self {
             Ok(o) => o,
             Err(err) => {
-                eprintln!("{}", err.msg);
+                print_err(&err);
                 std::process::exit(err.status);
             }
         }
@@ -0,0 +1,445 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    spanned::Spanned, Attribute, Data, DeriveInput, Error, Expr, Fields, GenericArgument, LitChar,
+    LitStr, PathArguments, Result, Type,
+};
+
+pub fn derive_command(input: DeriveInput) -> Result<TokenStream> {
+    let ident = &input.ident;
+    let settings = CommandSettings::from_attrs(&input.attrs)?;
+    let name = match &settings.name {
+        Some(name) => name.value(),
+        None => kebab_case(&ident.to_string()),
+    };
+    let description = match doc_comment(&input.attrs) {
+        Some(doc) => quote!(Some(#doc)),
+        None => quote!(None),
+    };
+
+    let build = match &input.data {
+        Data::Struct(data) => {
+            let command =
+                inputs_command(&data.fields, quote!(base), quote!(Self), quote!(handler))?;
+            quote! {
+                let mut handler = handler;
+                #command
+            }
+        }
+        Data::Enum(data) => {
+            let mut subcommands = vec![];
+            for variant in &data.variants {
+                let variant_ident = &variant.ident;
+                let variant_name = kebab_case(&variant_ident.to_string());
+                let variant_doc = doc_comment(&variant.attrs);
+
+                let subcommand = match &variant.fields {
+                    Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                        let inner = &fields.unnamed[0].ty;
+                        let description = match variant_doc {
+                            Some(doc) => quote!(Some(#doc)),
+                            None => quote!(<#inner as ::cli_rs::command::CommandDef>::DESCRIPTION),
+                        };
+
+                        quote! {
+                            let base = ::cli_rs::command::Command::name(#variant_name);
+                            let base = match #description {
+                                Some(description) => base.description(description),
+                                None => base,
+                            };
+                            <#inner as ::cli_rs::command::CommandDef>::build(base, move |inner| {
+                                (&mut *handler.borrow_mut())(Self::#variant_ident(inner))
+                            })
+                        }
+                    }
+                    Fields::Unnamed(fields) => {
+                        return Err(Error::new(
+                            fields.span(),
+                            "subcommand variants hold a single type that derives Command",
+                        ))
+                    }
+                    fields => {
+                        let base = match variant_doc {
+                            Some(doc) => quote! {
+                                ::cli_rs::command::Command::name(#variant_name).description(#doc)
+                            },
+                            None => quote!(::cli_rs::command::Command::name(#variant_name)),
+                        };
+                        let handler = quote!((&mut *handler.borrow_mut()));
+                        inputs_command(fields, base, quote!(Self::#variant_ident), handler)?
+                    }
+                };
+
+                subcommands.push(quote! {
+                    .subcommand({
+                        let handler = handler.clone();
+                        #subcommand
+                    })
+                });
+            }
+
+            quote! {
+                let handler = ::std::rc::Rc::new(::std::cell::RefCell::new(handler));
+                base #(#subcommands)*
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new(
+                ident.span(),
+                "Command can only be derived for structs and enums",
+            ))
+        }
+    };
+
+    let customized = settings.version.is_some()
+        || settings.completions
+        || settings.negative_numbers
+        || settings.infer_prefixes;
+    let base = if customized {
+        let version = settings.version.map(|version| quote!(.version(#version)));
+        let completions = settings.completions.then(|| quote!(.with_completions()));
+        let negative_numbers = settings
+            .negative_numbers
+            .then(|| quote!(.allow_negative_numbers()));
+        let infer_prefixes = settings.infer_prefixes.then(|| quote!(.infer_prefixes()));
+
+        quote! {
+            fn base<'a>() -> ::cli_rs::command::Command<'a> {
+                let base = ::cli_rs::command::Command::name(Self::NAME);
+                let base = match Self::DESCRIPTION {
+                    Some(description) => base.description(description),
+                    None => base,
+                };
+                base #version #completions #negative_numbers #infer_prefixes
+            }
+        }
+    } else {
+        quote!()
+    };
+
+    Ok(quote! {
+        impl ::cli_rs::command::CommandDef for #ident {
+            const NAME: &'static str = #name;
+            const DESCRIPTION: Option<&'static str> = #description;
+
+            #base
+
+            fn build<'a, F>(
+                base: ::cli_rs::command::Command<'a>,
+                handler: F,
+            ) -> impl ::cli_rs::parser::Cmd + 'a
+            where
+                F: FnMut(Self) -> ::cli_rs::cli_error::CliResult<()> + 'a,
+            {
+                #build
+            }
+        }
+    })
+}
+
+/// `base` with an input per field, and a handler that rebuilds the value with `ctor` and passes it
+/// to `handler`
+fn inputs_command(
+    fields: &Fields,
+    base: TokenStream,
+    ctor: TokenStream,
+    handler: TokenStream,
+) -> Result<TokenStream> {
+    let fields = match fields {
+        Fields::Named(fields) => fields.named.iter().collect::<Vec<_>>(),
+        Fields::Unit => {
+            return Ok(quote! {
+                #base.handler(move || #handler(#ctor))
+            })
+        }
+        Fields::Unnamed(fields) => {
+            return Err(Error::new(
+                fields.span(),
+                "Command fields must be named, they become the names of inputs",
+            ))
+        }
+    };
+
+    let mut inputs = vec![];
+    let mut params = vec![];
+    let mut values = vec![];
+    for field in fields {
+        let field_ident = field.ident.as_ref().unwrap();
+        let param = format_ident!("in_{}", field_ident);
+        let (input, getter) = field_input(field_ident.to_string(), &field.ty, &field.attrs)?;
+
+        inputs.push(quote!(.input(#input)));
+        values.push(quote!(#field_ident: #param.#getter()));
+        params.push(param);
+    }
+
+    // (in_a, (in_b, ())), so there's no limit on the number of fields
+    let pattern = params
+        .iter()
+        .rev()
+        .fold(quote!(()), |tail, param| quote!((#param, #tail)));
+
+    Ok(quote! {
+        #base
+            #(#inputs)*
+            .handler_list(move |#pattern| #handler(#ctor { #(#values),* }))
+    })
+}
+
+/// the `Arg` or `Flag` expression for a single field, and the method that reads its value back
+fn field_input(
+    field_name: String,
+    ty: &Type,
+    attrs: &[Attribute],
+) -> Result<(TokenStream, TokenStream)> {
+    let name = field_name.replace('_', "-");
+    let settings = InputSettings::from_attrs(attrs)?;
+
+    let mut builders = vec![];
+    if let Some(doc) = doc_comment(attrs) {
+        builders.push(quote!(.description(#doc)));
+    }
+    if let Some(env) = &settings.env {
+        builders.push(quote!(.env(#env)));
+    }
+
+    if settings.value_enum && !matches!(wrapped_type(ty), None | Some(("Option", _))) {
+        return Err(Error::new(
+            ty.span(),
+            "value_enum fields hold a single value, or an Option of one",
+        ));
+    }
+
+    let mut getter = quote!(get);
+    let input = if settings.flag {
+        match settings.short {
+            Some(Some(short)) => builders.push(quote!(.short(#short))),
+            Some(None) => builders.push(quote!(.no_short())),
+            None => {}
+        }
+
+        if settings.count {
+            if settings.env.is_some() {
+                return Err(Error::new(ty.span(), "counting flags don't read env vars"));
+            }
+            return Ok((
+                quote!(::cli_rs::count_flag::CountFlag::new(#name) #(#builders)*),
+                getter,
+            ));
+        }
+
+        match wrapped_type(ty) {
+            // an `Option` flag is `None` unless it was actually given
+            Some(("Option", inner)) if settings.value_enum => {
+                getter = quote!(get_opt);
+                quote!(::cli_rs::flag::Flag::<#inner>::value_enum(#name))
+            }
+            Some(("Option", inner)) if is_bool(inner) => {
+                getter = quote!(get_opt);
+                quote!(::cli_rs::flag::Flag::bool(#name))
+            }
+            Some(("Option", inner)) => {
+                getter = quote!(get_opt);
+                quote!(::cli_rs::flag::Flag::<#inner>::new(#name))
+            }
+            Some(("Vec", inner)) => quote!(::cli_rs::list_flag::ListFlag::<#inner>::new(#name)),
+            // without a default to fall back on, a plain value enum field has to be given
+            _ if settings.value_enum => {
+                quote!(::cli_rs::flag::Flag::<#ty>::value_enum(#name).required())
+            }
+            _ if is_bool(ty) => quote!(::cli_rs::flag::Flag::bool(#name)),
+            _ => quote!(::cli_rs::flag::Flag::<#ty>::new(#name)),
+        }
+    } else {
+        if settings.count {
+            return Err(Error::new(
+                ty.span(),
+                "only flags count, mark the field #[flag]",
+            ));
+        }
+        if settings.short.is_some() {
+            return Err(Error::new(
+                ty.span(),
+                "only flags have short names, mark the field #[flag]",
+            ));
+        }
+
+        // the handler owns the value it builds, so move it out of the input rather than clone
+        match wrapped_type(ty) {
+            Some(("Option", _)) if settings.value_enum => {
+                return Err(Error::new(
+                    ty.span(),
+                    "optional args can't be a value_enum, make the field a #[flag]",
+                ));
+            }
+            Some(("Option", inner)) => quote!(::cli_rs::opt_arg::OptArg::<#inner>::name(#name)),
+            Some(("Vec", inner)) => {
+                getter = quote!(take);
+                quote!(::cli_rs::list_arg::ListArg::<#inner>::name(#name))
+            }
+            _ if settings.value_enum => {
+                getter = quote!(take);
+                quote!(::cli_rs::arg::Arg::<#ty>::value_enum(#name))
+            }
+            _ => {
+                getter = quote!(take);
+                quote!(::cli_rs::arg::Arg::<#ty>::name(#name))
+            }
+        }
+    };
+
+    Ok((quote!(#input #(#builders)*), getter))
+}
+
+#[derive(Default)]
+struct CommandSettings {
+    name: Option<LitStr>,
+    version: Option<LitStr>,
+    completions: bool,
+    negative_numbers: bool,
+    infer_prefixes: bool,
+}
+
+impl CommandSettings {
+    /// reads `#[command(name = "...", version = "...", completions, allow_negative_numbers,
+    /// infer_prefixes)]`
+    fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut settings = Self::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("command")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    settings.name = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("version") {
+                    settings.version = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("completions") {
+                    settings.completions = true;
+                } else if meta.path.is_ident("allow_negative_numbers") {
+                    settings.negative_numbers = true;
+                } else if meta.path.is_ident("infer_prefixes") {
+                    settings.infer_prefixes = true;
+                } else {
+                    return Err(meta.error(
+                        "expected `name`, `version`, `completions`, `allow_negative_numbers` or `infer_prefixes`",
+                    ));
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(settings)
+    }
+}
+
+#[derive(Default)]
+struct InputSettings {
+    flag: bool,
+    /// `Some(None)` turns the short name off
+    short: Option<Option<LitChar>>,
+    env: Option<LitStr>,
+    count: bool,
+    value_enum: bool,
+}
+
+impl InputSettings {
+    /// reads `#[flag(short = 'x', no_short, env = "VAR", count, value_enum)]` and
+    /// `#[arg(env = "VAR", value_enum)]`
+    fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut settings = Self::default();
+        for attr in attrs {
+            if attr.path().is_ident("flag") {
+                settings.flag = true;
+            } else if !attr.path().is_ident("arg") {
+                continue;
+            }
+
+            if !matches!(attr.meta, syn::Meta::List(_)) {
+                continue;
+            }
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("short") {
+                    settings.short = Some(Some(meta.value()?.parse()?));
+                } else if meta.path.is_ident("no_short") {
+                    settings.short = Some(None);
+                } else if meta.path.is_ident("env") {
+                    settings.env = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("count") {
+                    settings.count = true;
+                } else if meta.path.is_ident("value_enum") {
+                    settings.value_enum = true;
+                } else {
+                    return Err(
+                        meta.error("expected `short`, `no_short`, `env`, `count` or `value_enum`")
+                    );
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(settings)
+    }
+}
+
+/// doc comments, joined into a single line
+pub(crate) fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(nv) => match &nv.value {
+                Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(doc),
+                    ..
+                }) => Some(doc.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
+
+fn is_bool(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.path.is_ident("bool"))
+}
+
+/// for `Option<T>` and `Vec<T>`, the wrapper's name and `T`
+fn wrapped_type(ty: &Type) -> Option<(&'static str, &Type)> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    let wrapper = match segment.ident.to_string().as_str() {
+        "Option" => "Option",
+        "Vec" => "Vec",
+        _ => return None,
+    };
+
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) => Some((wrapper, inner)),
+        _ => None,
+    }
+}
+
+/// `EditFile` becomes `edit-file`
+pub(crate) fn kebab_case(ident: &str) -> String {
+    let mut name = String::new();
+    for (i, c) in ident.chars().enumerate() {
+        if c.is_uppercase() {
+            if i != 0 {
+                name.push('-');
+            }
+            name.extend(c.to_lowercase());
+        } else if c == '_' {
+            name.push('-');
+        } else {
+            name.push(c);
+        }
+    }
+    name
+}
@@ -0,0 +1,143 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{spanned::Spanned, Attribute, Data, DeriveInput, Error, Fields, LitStr, Result};
+
+use crate::derive::{doc_comment, kebab_case};
+
+pub fn derive_value_enum(input: DeriveInput) -> Result<TokenStream> {
+    let ident = &input.ident;
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new(
+            ident.span(),
+            "ValueEnum can only be derived for enums",
+        ));
+    };
+
+    let impls = EnumSettings::from_attrs(&input.attrs)?;
+    let mut variants = vec![];
+    let mut possible_values = vec![];
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new(
+                variant.fields.span(),
+                "ValueEnum variants can't hold fields",
+            ));
+        }
+
+        let variant_ident = &variant.ident;
+        let settings = ValueSettings::from_attrs(&variant.attrs)?;
+        let name = match &settings.name {
+            Some(name) => name.value(),
+            None => kebab_case(&variant_ident.to_string()),
+        };
+        let aliases = &settings.aliases;
+        let description = doc_comment(&variant.attrs).map(|doc| quote!(.description(#doc)));
+
+        variants.push(quote!(Self::#variant_ident));
+        possible_values.push(quote! {
+            Self::#variant_ident => ::cli_rs::value_enum::PossibleValue::new(#name)
+                #(.alias(#aliases))*
+                #description
+        });
+    }
+
+    let from_str = impls.from_str.then(|| {
+        quote! {
+            impl ::std::str::FromStr for #ident {
+                type Err = ::std::string::String;
+
+                fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+                    <Self as ::cli_rs::value_enum::ValueEnum>::from_name(s).ok_or_else(|| {
+                        let names = <Self as ::cli_rs::value_enum::ValueEnum>::possible_values()
+                            .into_iter()
+                            .map(|value| value.name)
+                            .collect::<::std::vec::Vec<_>>();
+                        ::std::format!("expected one of {}", names.join(", "))
+                    })
+                }
+            }
+        }
+    });
+    let display = impls.display.then(|| {
+        quote! {
+            impl ::std::fmt::Display for #ident {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                    let value = <Self as ::cli_rs::value_enum::ValueEnum>::possible_value(self);
+                    f.write_str(&value.name)
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl ::cli_rs::value_enum::ValueEnum for #ident {
+            fn variants() -> ::std::vec::Vec<Self> {
+                ::std::vec![#(#variants),*]
+            }
+
+            fn possible_value(&self) -> ::cli_rs::value_enum::PossibleValue {
+                match self {
+                    #(#possible_values),*
+                }
+            }
+        }
+
+        #from_str
+        #display
+    })
+}
+
+#[derive(Default)]
+struct EnumSettings {
+    from_str: bool,
+    display: bool,
+}
+
+impl EnumSettings {
+    /// reads `#[value(from_str, display)]` on the enum, which opt into implementing `FromStr`
+    /// and `Display` with the values' names
+    fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut settings = Self::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("value")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("from_str") {
+                    settings.from_str = true;
+                } else if meta.path.is_ident("display") {
+                    settings.display = true;
+                } else {
+                    return Err(meta.error("expected `from_str` or `display`"));
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(settings)
+    }
+}
+
+#[derive(Default)]
+struct ValueSettings {
+    name: Option<LitStr>,
+    aliases: Vec<LitStr>,
+}
+
+impl ValueSettings {
+    /// reads `#[value(name = "...", alias = "...")]`, `alias` may be repeated
+    fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut settings = Self::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("value")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    settings.name = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("alias") {
+                    settings.aliases.push(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("expected `name` or `alias`"));
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(settings)
+    }
+}
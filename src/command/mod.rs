@@ -1,22 +1,82 @@
-use cli_rs_command_gen::command;
+use cli_rs_command_gen::handler;
+pub use cli_rs_command_gen::Command;
+use std::collections::HashMap;
 use std::fmt::Write;
-use std::str::FromStr;
-
-pub type Command<'a> = Command0<'a>;
 
 // todo: use CliResult, also in macro
 pub trait ParserInfo {
     fn docs(&self) -> &DocInfo;
+    fn docs_mut(&mut self) -> &mut DocInfo;
     fn symbols(&mut self) -> Vec<&mut dyn Input>;
     fn subcommand_docs(&self) -> Vec<DocInfo>;
-    fn parse_subcommand(&mut self, sub_idx: usize, tokens: &[String]) -> Result<(), CliError>;
+    fn parse_subcommand(
+        &mut self,
+        sub_idx: usize,
+        tokens: &[String],
+        env: &HashMap<String, String>,
+        inherited: Vec<&mut dyn Input>,
+    ) -> Result<(), CliError>;
     fn complete_subcommand(
         &mut self,
         sub_idx: usize,
         tokens: &[String],
+        inherited: Vec<&mut dyn Input>,
     ) -> Result<Vec<CompOut>, CliError>;
     fn call_handler(&mut self) -> CliResult<()>;
     fn push_parent(&mut self, parents: &[String]);
+    /// turns on prefix matching for this command and everything beneath it
+    fn infer_prefixes_below(&mut self);
+    /// panics if a global a parent passes down shares a name with an input of this command or
+    /// anything beneath it
+    fn check_inherited(&mut self, inherited: &[&mut dyn Input]);
+}
+
+/// a command described by a type, usually through `#[derive(Command)]`. A struct's fields become
+/// its inputs: positional args by default, `Option<T>` and `Vec<T>` fields become an `OptArg` and a
+/// `ListArg`, and `#[flag]` fields become flags. An enum's variants become subcommands, holding
+/// either another type that derives `Command` or fields of their own. Doc comments become
+/// descriptions.
+///
+/// ```ignore
+/// /// a secure, private notebook
+/// #[derive(Command)]
+/// #[command(version = "0.9.0", completions)]
+/// enum Lockbook {
+///     /// edit a document
+///     Edit {
+///         #[flag(env = "LOCKBOOK_EDITOR")]
+///         editor: String,
+///         target: String,
+///     },
+///     Sync,
+/// }
+///
+/// Lockbook::handler(|lockbook| match lockbook { ... }).parse().exit();
+/// ```
+pub trait CommandDef: Sized {
+    const NAME: &'static str;
+    const DESCRIPTION: Option<&'static str>;
+
+    /// the command this type is built on when it's the root of a cli
+    fn base<'a>() -> Command<'a> {
+        let base = Command::name(Self::NAME);
+        match Self::DESCRIPTION {
+            Some(description) => base.description(description),
+            None => base,
+        }
+    }
+
+    /// adds this type's inputs or subcommands to `base`, calling `handler` with the parsed value
+    fn build<'a, F>(base: Command<'a>, handler: F) -> impl Cmd + 'a
+    where
+        F: FnMut(Self) -> CliResult<()> + 'a;
+
+    fn handler<'a, F>(handler: F) -> impl Cmd + 'a
+    where
+        F: FnMut(Self) -> CliResult<()> + 'a,
+    {
+        Self::build(Self::base(), handler)
+    }
 }
 
 #[derive(Default, Debug, Clone)]
@@ -25,6 +85,39 @@ pub struct DocInfo {
     pub(crate) version: Option<String>,
     pub(crate) description: Option<String>,
     pub(crate) parents: Vec<String>,
+    /// whether `-5` goes to an arg before any flag is tried
+    pub(crate) negative_numbers: bool,
+    /// whether a unique prefix of a subcommand or long flag name stands for the whole name
+    pub(crate) infer_prefixes: bool,
+    /// other names a parent accepts for this subcommand, help lists them but completions don't
+    pub(crate) aliases: Vec<String>,
+    /// kept out of its parent's help and completions, but still parsed
+    pub(crate) hidden: bool,
+    /// checked between the inputs once they're all parsed, by input name
+    pub(crate) rules: Vec<Rule>,
+    /// the rules of the commands above, set as this one is parsed since the globals they name are
+    /// parsed here
+    pub(crate) inherited_rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Rule {
+    /// the two inputs can't both be given
+    Conflicts(String, String),
+    /// the first input can't be given without the second
+    Requires(String, String),
+    /// one and only one of the inputs must be given
+    ExactlyOne(Vec<String>),
+}
+
+impl Rule {
+    /// the inputs the rule names
+    fn inputs(&self) -> Vec<&str> {
+        match self {
+            Rule::Conflicts(input, other) | Rule::Requires(input, other) => vec![input, other],
+            Rule::ExactlyOne(inputs) => inputs.iter().map(String::as_str).collect(),
+        }
+    }
 }
 
 impl DocInfo {
@@ -37,28 +130,117 @@ impl DocInfo {
         write!(path, "{}", self.name).unwrap();
         path
     }
+
+    /// the rules checked when this command is parsed, its parents' and then its own
+    pub(crate) fn all_rules(&self) -> Vec<Rule> {
+        let rules = self.inherited_rules.iter().chain(&self.rules);
+        rules.cloned().collect()
+    }
+
+    /// whether `token` is this command's name or one of its aliases
+    pub fn answers_to(&self, token: &str) -> bool {
+        self.name == token || self.aliases.iter().any(|alias| alias == token)
+    }
 }
 
-#[derive(PartialEq, Copy, Clone, Debug)]
-pub enum CompletionMode {
-    Bash,
-    Fish,
-    Zsh,
+/// two inputs answering to the same `-x` is a mistake in how the command was built, not in how it
+/// was invoked, so this panics rather than leaving the parser to pick one of them
+fn check_short_names(cmd_path: &str, symbols: &[&mut dyn Input]) {
+    for (i, symbol) in symbols.iter().enumerate() {
+        let Some(short) = symbol.short_name() else {
+            continue;
+        };
+
+        for other in &symbols[..i] {
+            if other.short_name() == Some(short) {
+                panic!(
+                    "{cmd_path}: --{} and --{} both use -{short}, give one of them a different .short() or .no_short()",
+                    other.display_name(),
+                    symbol.display_name(),
+                );
+            }
+        }
+    }
 }
 
-impl FromStr for CompletionMode {
-    type Err = ();
+/// a rule naming an input the command doesn't have is a mistake in how it was built, so like a
+/// short name used twice this panics. It's checked when the command is parsed, once the globals
+/// it inherits are known, a subcommand's rule can name them and a parent's rule is checked in
+/// the subcommand that's run
+pub(crate) fn check_rule_inputs(cmd_path: &str, rules: &[Rule], symbols: &[&mut dyn Input]) {
+    for name in rules.iter().flat_map(Rule::inputs) {
+        if !symbols.iter().any(|symbol| symbol.display_name() == name) {
+            panic!("{cmd_path}: a rule names {name}, which isn't one of its inputs");
+        }
+    }
+}
+
+/// positional values are handed out in order, so a positional after one that takes every remaining
+/// value would never get one, and a required one after an optional one would take the value meant
+/// for the optional one. Both are mistakes in how the command was built, so this panics.
+fn check_positional_order(cmd_path: &str, symbols: &[&mut dyn Input]) {
+    let positionals = symbols
+        .iter()
+        .filter(|symbol| symbol.type_name() == InputType::Arg)
+        .collect::<Vec<_>>();
+
+    for (i, symbol) in positionals.iter().enumerate().skip(1) {
+        let before = &positionals[..i];
+        if let Some(list) = before.iter().find(|other| other.is_repeatable()) {
+            panic!(
+                "{cmd_path}: <{}> takes every remaining value, so it must be the last positional input, but <{}> comes after it",
+                list.display_name(),
+                symbol.display_name(),
+            );
+        }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "bash" => Ok(Self::Bash),
-            "zsh" => Ok(Self::Zsh),
-            "fish" => Ok(Self::Fish),
-            _ => panic!("unsuppored shell, choices are bash, zsh, and fish"),
+        if symbol.has_default() {
+            continue;
+        }
+        if let Some(optional) = before.iter().find(|other| other.has_default()) {
+            panic!(
+                "{cmd_path}: [{}] is optional, so it must come after every required positional input, but <{}> comes after it",
+                optional.display_name(),
+                symbol.display_name(),
+            );
         }
     }
 }
 
+/// like `check_short_names`, for the globals a command inherits: `-v` for a parent's --verbose
+/// and a subcommand's --validate would leave `lb sync -v` setting whichever the parser saw first
+fn check_inherited_names(cmd_path: &str, symbols: &[&mut dyn Input], inherited: &[&mut dyn Input]) {
+    for symbol in symbols {
+        for global in inherited {
+            let clash =
+                if symbol.short_name().is_some() && symbol.short_name() == global.short_name() {
+                    format!("-{}", symbol.short_name().unwrap())
+                } else if symbol.type_name() == InputType::Flag
+                    && global.type_name() == InputType::Flag
+                    && symbol.display_name() == global.display_name()
+                {
+                    format!("--{}", symbol.display_name())
+                } else {
+                    continue;
+                };
+
+            panic!(
+                "{cmd_path}: --{} and the inherited global --{} both use {clash}, rename one of them or give it a different .short() or .no_short()",
+                symbol.display_name(),
+                global.display_name(),
+            );
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug, ValueEnum)]
+#[value(from_str)]
+pub enum CompletionMode {
+    Bash,
+    Fish,
+    Zsh,
+}
+
 impl CompletionMode {
     // thanks @ad-tra
     pub fn print_completion(&self, name: &str) {
@@ -98,14 +280,310 @@ function _{name} {{
 use crate::{
     arg::Arg,
     cli_error::{CliError, CliResult},
-    input::Input,
+    global::Global,
+    input::{Input, InputType},
     parser::{Cmd, CompOut},
+    value_enum::ValueEnum,
 };
 
-command!(0);
-command!(1);
-command!(2);
-command!(3);
-command!(4);
-command!(5);
-command!(6);
+/// the inputs of a command as a nested list, `(in1, (in2, (in3, ())))`, so a command can have
+/// any number of them while each keeps its own type
+pub trait Inputs {
+    fn symbols(&mut self) -> Vec<&mut dyn Input>;
+}
+
+impl Inputs for () {
+    fn symbols(&mut self) -> Vec<&mut dyn Input> {
+        vec![]
+    }
+}
+
+impl<H: Input, T: Inputs> Inputs for (H, T) {
+    fn symbols(&mut self) -> Vec<&mut dyn Input> {
+        let (head, tail) = self;
+        let mut symbols: Vec<&mut dyn Input> = vec![head];
+        symbols.extend(tail.symbols());
+        symbols
+    }
+}
+
+/// adds an input to the end of an `Inputs` list
+pub trait Append<N> {
+    type Output;
+
+    fn append(self, next: N) -> Self::Output;
+}
+
+impl<N> Append<N> for () {
+    type Output = (N, ());
+
+    fn append(self, next: N) -> Self::Output {
+        (next, ())
+    }
+}
+
+impl<H, T: Append<N>, N> Append<N> for (H, T) {
+    type Output = (H, T::Output);
+
+    fn append(self, next: N) -> Self::Output {
+        (self.0, self.1.append(next))
+    }
+}
+
+type Callback<'a, I> = Box<dyn FnMut(&mut I) -> CliResult<()> + 'a>;
+
+pub struct Command<'a, I = ()> {
+    pub docs: DocInfo,
+
+    pub subcommands: Vec<Box<dyn Cmd + 'a>>,
+    pub globals: Vec<Global<dyn Input + 'a>>,
+    pub handler: Option<Callback<'a, I>>,
+
+    pub inputs: I,
+}
+
+impl<'a, I: Inputs> ParserInfo for Command<'a, I> {
+    fn docs(&self) -> &DocInfo {
+        &self.docs
+    }
+
+    fn docs_mut(&mut self) -> &mut DocInfo {
+        &mut self.docs
+    }
+
+    fn symbols(&mut self) -> Vec<&mut dyn Input> {
+        let mut symbols = self.inputs.symbols();
+        symbols.extend(self.globals.iter_mut().map(|g| g as &mut dyn Input));
+        symbols
+    }
+
+    fn subcommand_docs(&self) -> Vec<DocInfo> {
+        self.subcommands.iter().map(|s| s.docs().clone()).collect()
+    }
+
+    fn call_handler(&mut self) -> CliResult<()> {
+        if let Some(handler) = &mut self.handler {
+            handler(&mut self.inputs)
+        } else {
+            Err(CliError::from(format!(
+                "No handler hooked up to {}",
+                self.docs.cmd_path()
+            )))
+        }
+    }
+
+    fn push_parent(&mut self, parents: &[String]) {
+        self.docs.parents.extend_from_slice(parents);
+    }
+
+    fn infer_prefixes_below(&mut self) {
+        self.docs.infer_prefixes = true;
+        for sub in &mut self.subcommands {
+            sub.infer_prefixes_below();
+        }
+    }
+
+    fn check_inherited(&mut self, inherited: &[&mut dyn Input]) {
+        let cmd_path = self.docs.cmd_path();
+        check_inherited_names(&cmd_path, &self.symbols(), inherited);
+        for sub in &mut self.subcommands {
+            sub.check_inherited(inherited);
+        }
+    }
+
+    fn complete_subcommand(
+        &mut self,
+        sub_idx: usize,
+        tokens: &[String],
+        inherited: Vec<&mut dyn Input>,
+    ) -> Result<Vec<CompOut>, CliError> {
+        let mut globals: Vec<&mut dyn Input> =
+            inherited.into_iter().map(|g| g as &mut dyn Input).collect();
+        globals.extend(self.globals.iter_mut().map(|g| g as &mut dyn Input));
+        let sub = &mut self.subcommands[sub_idx];
+        sub.docs_mut().inherited_rules = self.docs.all_rules();
+        sub.complete_args_inherited(tokens, globals)
+    }
+
+    fn parse_subcommand(
+        &mut self,
+        sub_idx: usize,
+        tokens: &[String],
+        env: &HashMap<String, String>,
+        inherited: Vec<&mut dyn Input>,
+    ) -> Result<(), CliError> {
+        let mut globals: Vec<&mut dyn Input> =
+            inherited.into_iter().map(|g| g as &mut dyn Input).collect();
+        globals.extend(self.globals.iter_mut().map(|g| g as &mut dyn Input));
+        let sub = &mut self.subcommands[sub_idx];
+        sub.docs_mut().inherited_rules = self.docs.all_rules();
+        sub.parse_args_inherited(tokens, env, globals)
+    }
+}
+
+impl<'a> Command<'a> {
+    pub fn name(name: &str) -> Self {
+        Self {
+            docs: DocInfo {
+                name: name.to_string(),
+                ..Default::default()
+            },
+            subcommands: vec![],
+            globals: vec![],
+            handler: None,
+            inputs: (),
+        }
+    }
+}
+
+impl<'a, I: Inputs> Command<'a, I> {
+    pub fn with_completions(self) -> Self {
+        let name = self.docs.name.clone();
+
+        self.subcommand(
+            Command::name("completions")
+                .description("generate completions for a given shell")
+                .input(Arg::<CompletionMode>::value_enum("shell"))
+                .handler(move |shell| {
+                    shell.get().print_completion(&name);
+                    Ok(())
+                }),
+        )
+    }
+
+    pub fn version(mut self, version: &str) -> Self {
+        self.docs.version = Some(version.to_string());
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.docs.description = Some(description.to_string());
+        self
+    }
+
+    /// reads tokens like `-5` and `-0.5` as args rather than flags. Without this a negative number
+    /// only becomes an arg once no flag answers to it.
+    pub fn allow_negative_numbers(mut self) -> Self {
+        self.docs.negative_numbers = true;
+        self
+    }
+
+    /// accepts a unique prefix of a subcommand or long flag name in place of the whole name, `sy`
+    /// for `sync`. A prefix that starts several names is an error listing them. Applies to every
+    /// subcommand beneath this one too.
+    pub fn infer_prefixes(mut self) -> Self {
+        self.infer_prefixes_below();
+        self
+    }
+
+    /// another name this command answers to as a subcommand, `rm` for `delete`
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.docs.aliases.push(alias.to_string());
+        self
+    }
+
+    /// keeps this subcommand out of its parent's help and completions, for internal or debug
+    /// commands that are still invoked by name
+    pub fn hidden(mut self) -> Self {
+        self.docs.hidden = true;
+        self
+    }
+
+    /// fails parsing when both inputs are given, `.conflicts("public", "private")`
+    pub fn conflicts(mut self, input: &str, other: &str) -> Self {
+        let rule = Rule::Conflicts(input.to_string(), other.to_string());
+        self.docs.rules.push(rule);
+        self
+    }
+
+    /// fails parsing when `input` is given without `required`, `.requires("user", "role")`
+    pub fn requires(mut self, input: &str, required: &str) -> Self {
+        let rule = Rule::Requires(input.to_string(), required.to_string());
+        self.docs.rules.push(rule);
+        self
+    }
+
+    /// fails parsing unless exactly one of `inputs` is given
+    pub fn exactly_one_of(mut self, inputs: &[&str]) -> Self {
+        let rule = Rule::ExactlyOne(inputs.iter().map(|input| input.to_string()).collect());
+        self.docs.rules.push(rule);
+        self
+    }
+
+    pub fn input<N>(self, input: N) -> Command<'a, I::Output>
+    where
+        N: Input,
+        I: Append<N>,
+        I::Output: Inputs,
+    {
+        let mut command = Command {
+            docs: self.docs,
+            subcommands: self.subcommands,
+            globals: self.globals,
+            handler: None,
+            inputs: self.inputs.append(input),
+        };
+
+        let cmd_path = command.docs.cmd_path();
+        check_short_names(&cmd_path, &command.symbols());
+        check_positional_order(&cmd_path, &command.symbols());
+        command
+    }
+
+    /// like `handler`, but receives every input as the nested list `(in1, (in2, (in3, ())))`.
+    /// Works for any number of inputs, where `handler` stops at 16.
+    pub fn handler_list<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(&mut I) -> CliResult<()> + 'a,
+    {
+        self.handler = Some(Box::new(handler));
+        self
+    }
+
+    /// declares an input that this command and every subcommand beneath it accepts
+    pub fn global<G: Input + 'a>(mut self, global: &Global<G>) -> Self {
+        self.globals.push(global.erase());
+        check_short_names(&self.docs.cmd_path(), &self.symbols());
+
+        let mut erased = global.erase();
+        for sub in &mut self.subcommands {
+            sub.check_inherited(&[&mut erased]);
+        }
+        self
+    }
+
+    pub fn subcommand<C: Cmd + 'a>(mut self, mut sub: C) -> Self {
+        sub.push_parent(&self.docs.parents);
+        sub.push_parent(&[self.docs.name.clone()]);
+        if self.docs.infer_prefixes {
+            sub.infer_prefixes_below();
+        }
+
+        let globals: Vec<&mut dyn Input> = self
+            .globals
+            .iter_mut()
+            .map(|g| g as &mut dyn Input)
+            .collect();
+        sub.check_inherited(&globals);
+        self.subcommands.push(Box::new(sub));
+        self
+    }
+}
+
+handler!(0);
+handler!(1);
+handler!(2);
+handler!(3);
+handler!(4);
+handler!(5);
+handler!(6);
+handler!(7);
+handler!(8);
+handler!(9);
+handler!(10);
+handler!(11);
+handler!(12);
+handler!(13);
+handler!(14);
+handler!(15);
+handler!(16);
@@ -0,0 +1,105 @@
+use crate::{
+    cli_error::{CliError, CliResult},
+    flag::split_token,
+    input::{Input, InputType},
+};
+
+/// a flag counting how many times it was given, `-vvv` or `--verbose --verbose`
+pub struct CountFlag {
+    pub name: String,
+    pub description: Option<String>,
+    pub count: usize,
+    pub short: Option<char>,
+    pub hidden: bool,
+}
+
+impl CountFlag {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: None,
+            count: 0,
+            short: name.chars().next(),
+            hidden: false,
+        }
+    }
+
+    pub fn get(&self) -> usize {
+        self.count
+    }
+
+    flag_builders!();
+}
+
+impl Input for CountFlag {
+    fn parse(&mut self, token: &str) -> CliResult<bool> {
+        match split_token(&self.name, self.short, token) {
+            Some(Some(value)) => {
+                self.parse_value(value)?;
+                Ok(true)
+            }
+            Some(None) => {
+                self.count += 1;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn expects_value(&self, _token: &str) -> bool {
+        false
+    }
+
+    fn is_named(&self, token: &str) -> bool {
+        split_token(&self.name, self.short, token).is_some()
+    }
+
+    /// `--verbose=3` sets the count outright
+    fn parse_value(&mut self, value: &str) -> CliResult<()> {
+        self.count = value
+            .parse()
+            .map_err(|err| CliError::unparsable_because(value, &self.name, err))?;
+
+        Ok(())
+    }
+
+    fn display_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn short_name(&self) -> Option<char> {
+        self.short
+    }
+
+    fn type_name(&self) -> InputType {
+        InputType::Flag
+    }
+
+    fn parsed(&self) -> bool {
+        self.count > 0
+    }
+
+    fn is_repeatable(&self) -> bool {
+        true
+    }
+
+    fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    fn complete(&mut self, _value: &str) -> CliResult<Vec<String>> {
+        Ok(vec![])
+    }
+
+    fn is_bool_flag(&self) -> bool {
+        true
+    }
+
+    fn description(&self) -> Option<String> {
+        self.description.clone()
+    }
+
+    fn has_default(&self) -> bool {
+        true
+    }
+}
@@ -1,114 +1,211 @@
-use std::str::FromStr;
+use std::{fmt::Display, str::FromStr};
 
 use crate::{
     cli_error::{CliError, CliResult},
-    input::{Completor, Input, InputType},
+    input::{self, Completor, DefaultValue, Input, InputType, Parser, Validator},
+    value_enum::{self, PossibleValue, ValueEnum},
 };
 
 // todo existence
-// todo short flags with a space
-// todo short flag-sets
-pub struct Flag<'a, T: Default + FromStr + Clone> {
+pub struct Flag<'a, T: Clone> {
     pub name: String,
     pub description: Option<String>,
     pub value: Option<T>,
     pub bool_flag: bool,
+    pub short: Option<char>,
     pub completor: Option<Completor<'a>>,
+    pub env: Option<String>,
+    pub default_value: Option<DefaultValue<'a, T>>,
+    pub parser: Parser<'a, T>,
+    pub validators: Vec<Validator<'a, T>>,
+    pub hidden: bool,
+    pub possible_values: Vec<PossibleValue>,
+    /// parsing fails when the flag isn't given and has no default, set with `.required()`
+    pub required: bool,
 }
 
 impl<'a> Flag<'a, bool> {
     pub fn bool(name: &str) -> Self {
         Self {
-            name: name.to_string(),
-            value: None,
             bool_flag: true,
-            completor: None,
-            description: None,
+            ..Self::new(name).parse_details()
         }
     }
 }
 
-impl<'a, T: FromStr + Default + Clone> Flag<'a, T> {
+impl<'a, T> Flag<'a, T>
+where
+    T: FromStr + Default + Clone + 'a,
+{
+    /// a flag that is `T::default()` when it isn't given
     pub fn new(name: &str) -> Self {
+        Self {
+            default_value: Some(DefaultValue::hidden(T::default)),
+            ..Self::name(name)
+        }
+    }
+}
+
+impl<'a, T> Flag<'a, T>
+where
+    T: FromStr + Clone + 'a,
+{
+    /// a flag without a default, for types that don't implement `Default`. It's optional, read it
+    /// with `.get_opt()`, unless it's given a `.default()` or marked `.required()`.
+    pub fn name(name: &str) -> Self {
+        let flag = name.to_string();
+        Self::with_parser(name, move |value| {
+            value
+                .parse()
+                .map_err(|_| CliError::unparsable(value, &flag))
+        })
+    }
+}
+
+impl<'a, T> Flag<'a, T>
+where
+    T: FromStr + Clone + 'a,
+    T::Err: Display + 'static,
+{
+    value_builders!(parse_details);
+}
+
+impl<'a, T: ValueEnum + 'a> Flag<'a, T> {
+    /// one of `T`'s values, which completions offer and help lists, without a default
+    pub fn value_enum(name: &str) -> Self {
+        let flag = name.to_string();
+        Self {
+            possible_values: T::possible_values(),
+            ..Self::with_parser(name, move |value| value_enum::parse(value, &flag))
+        }
+    }
+}
+
+impl<'a, T: Clone> Flag<'a, T> {
+    /// like `name`, for types parsed by `parser` rather than `FromStr`
+    pub fn with_parser<F>(name: &str, parser: F) -> Self
+    where
+        F: FnMut(&str) -> CliResult<T> + 'a,
+    {
         Self {
             name: name.to_string(),
             value: None,
             bool_flag: false,
+            short: name.chars().next(),
             completor: None,
             description: None,
+            env: None,
+            default_value: None,
+            parser: Box::new(parser),
+            validators: vec![],
+            hidden: false,
+            possible_values: vec![],
+            required: false,
         }
     }
 
     pub fn get(&self) -> T {
-        self.value.clone().unwrap_or_default()
+        self.value.clone().unwrap_or_else(|| {
+            self.default_value
+                .as_ref()
+                .map(DefaultValue::get)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "--{} was read without a value or a default, use get_opt()",
+                        self.name
+                    )
+                })
+        })
     }
 
-    pub fn description(mut self, description: &str) -> Self {
-        self.description = Some(description.to_string());
+    /// fails parsing when the flag isn't given rather than falling back on a default, help marks
+    /// it as required and completions offer it first
+    pub fn required(mut self) -> Self {
+        self.default_value = None;
+        self.required = true;
         self
     }
 
-    pub fn completor<F>(mut self, completor: F) -> Self
-    where
-        F: FnMut(&str) -> CliResult<Vec<String>> + 'a,
-    {
-        self.completor = Some(Box::new(completor));
-        self
+    /// the value if the flag was given, on the command line or through its env var, so an unset
+    /// flag can be told apart from one set to the default
+    pub fn get_opt(&self) -> Option<T> {
+        self.value.clone()
     }
+
+    flag_builders!();
+    value_builders!();
 }
 
-impl<'a, T: FromStr + Default + Clone> Input for Flag<'a, T> {
-    // for short flags with a space
-    // should probably return a Result<bool, ParseError>
-    fn parse(&mut self, token: &str) -> CliResult<bool> {
-        // just handle the short flag first
-        if token.len() == 2 && self.bool_flag {
-            if &token[0..1] != "-" {
-                // todo should this be an error?
-                return Ok(false);
-            }
+/// if `token` names the flag `name` or `-{short}`, returns the value attached to it with `=`, if any
+pub(crate) fn split_token<'t>(
+    name: &str,
+    short: Option<char>,
+    token: &'t str,
+) -> Option<Option<&'t str>> {
+    let (given, value) = match token.split_once('=') {
+        Some((given, value)) => (given, Some(value)),
+        None => (token, None),
+    };
 
-            if token[1..2].to_uppercase() == self.name[0..1].to_uppercase() {
-                self.value = Some("true".parse().unwrap_or_else(|_| unreachable!()));
-                return Ok(true);
-            }
-        }
+    if let Some(long) = given.strip_prefix("--") {
+        return (long == name).then_some(value);
+    }
 
-        // make it safe to index anywhere
-        let min_size = self.name.len() + 2; // --
-        if token.len() < min_size {
-            return Ok(false);
-        }
+    let given = given.strip_prefix('-')?;
+    let mut chars = given.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if Some(c) == short => Some(value),
+        _ => None,
+    }
+}
 
-        // name check
-        let flag_length = self.name.len();
-        if token[2..flag_length + 2] != self.name {
-            return Ok(false);
+impl<'a, T: Clone> Input for Flag<'a, T> {
+    fn parse(&mut self, token: &str) -> CliResult<bool> {
+        if self.bool_flag && token.strip_prefix("--no-") == Some(&self.name) {
+            self.parse_value("false")?;
+            return Ok(true);
         }
 
-        // extract value
-        if let Some(eq_idx) = token.find('=') {
-            let value = &token[eq_idx + 1..].to_string();
+        match split_token(&self.name, self.short, token) {
+            Some(Some(value)) => {
+                self.parse_value(value)?;
+                Ok(true)
+            }
+            Some(None) if self.bool_flag => {
+                self.parse_value("true")?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
 
-            self.value = Some(value.parse().map_err(|_| {
-                CliError::from(format!("{} cannot be parsed for {}", value, self.name))
-            })?);
+    fn expects_value(&self, token: &str) -> bool {
+        !self.bool_flag && split_token(&self.name, self.short, token) == Some(None)
+    }
 
-            return Ok(true);
-        }
+    fn is_named(&self, token: &str) -> bool {
+        split_token(&self.name, self.short, token).is_some()
+            || (self.bool_flag && token.strip_prefix("--no-") == Some(&self.name))
+    }
 
-        if self.bool_flag {
-            self.value = Some("true".parse().unwrap_or_else(|_| unreachable!()));
-            return Ok(true);
+    fn parse_value(&mut self, value: &str) -> CliResult<()> {
+        let parsed = (self.parser)(value).map_err(|err| err.invalid_value(value, &self.name))?;
+        for validator in &self.validators {
+            validator.check(&parsed, value, &self.name)?;
         }
+        self.value = Some(parsed);
 
-        Ok(false)
+        Ok(())
     }
 
     fn display_name(&self) -> String {
         self.name.clone()
     }
 
+    fn short_name(&self) -> Option<char> {
+        self.short
+    }
+
     fn type_name(&self) -> InputType {
         InputType::Flag
     }
@@ -117,23 +214,43 @@ impl<'a, T: FromStr + Default + Clone> Input for Flag<'a, T> {
         self.value.is_some()
     }
 
+    fn default_help(&self) -> Option<String> {
+        self.default_value.as_ref().and_then(DefaultValue::help)
+    }
+
+    fn constraints(&self) -> Option<String> {
+        input::describe(&self.validators)
+    }
+
+    fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
     fn complete(&mut self, value: &str) -> CliResult<Vec<String>> {
         if let Some(completor) = &mut self.completor {
             completor(value)
         } else {
-            Ok(vec![])
+            Ok(value_enum::complete(&self.possible_values, value))
         }
     }
 
+    fn possible_values(&self) -> Vec<PossibleValue> {
+        self.possible_values.clone()
+    }
+
     fn is_bool_flag(&self) -> bool {
         self.bool_flag
     }
 
+    fn env_var(&self) -> Option<String> {
+        self.env.clone()
+    }
+
     fn description(&self) -> Option<String> {
         self.description.clone()
     }
 
     fn has_default(&self) -> bool {
-        true
+        !self.required || self.default_value.is_some()
     }
 }
@@ -0,0 +1,131 @@
+use std::{
+    cell::{Ref, RefCell},
+    rc::Rc,
+};
+
+use crate::{
+    cli_error::CliResult,
+    input::{Input, InputType},
+    value_enum::PossibleValue,
+};
+
+/// an input declared once on a parent command with `.global(&flag)` and accepted by every
+/// subcommand beneath it, before or after the subcommand's name. Clones share the parsed value,
+/// so a leaf handler can hold on to one and read it:
+///
+/// ```ignore
+/// let verbose = Global::new(Flag::bool("verbose"));
+/// Command::name("lockbook")
+///     .global(&verbose)
+///     .subcommand(Command::name("sync").handler(move || {
+///         if verbose.borrow().get() { ... }
+///     }))
+/// ```
+pub struct Global<I: ?Sized>(Rc<RefCell<I>>);
+
+impl<I: Input> Global<I> {
+    pub fn new(input: I) -> Self {
+        Self(Rc::new(RefCell::new(input)))
+    }
+
+    pub(crate) fn erase<'a>(&self) -> Global<dyn Input + 'a>
+    where
+        I: 'a,
+    {
+        Global(self.0.clone())
+    }
+}
+
+impl<I: ?Sized> Global<I> {
+    pub fn borrow(&self) -> Ref<'_, I> {
+        self.0.borrow()
+    }
+}
+
+impl<I: ?Sized> Clone for Global<I> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<I: Input + ?Sized> Input for Global<I> {
+    fn parsed(&self) -> bool {
+        self.0.borrow().parsed()
+    }
+
+    fn has_default(&self) -> bool {
+        self.0.borrow().has_default()
+    }
+
+    fn parse(&mut self, token: &str) -> CliResult<bool> {
+        self.0.borrow_mut().parse(token)
+    }
+
+    fn expects_value(&self, token: &str) -> bool {
+        self.0.borrow().expects_value(token)
+    }
+
+    fn parse_value(&mut self, value: &str) -> CliResult<()> {
+        self.0.borrow_mut().parse_value(value)
+    }
+
+    fn display_name(&self) -> String {
+        self.0.borrow().display_name()
+    }
+
+    fn short_name(&self) -> Option<char> {
+        self.0.borrow().short_name()
+    }
+
+    fn description(&self) -> Option<String> {
+        self.0.borrow().description()
+    }
+
+    fn type_name(&self) -> InputType {
+        self.0.borrow().type_name()
+    }
+
+    fn is_bool_flag(&self) -> bool {
+        self.0.borrow().is_bool_flag()
+    }
+
+    fn env_var(&self) -> Option<String> {
+        self.0.borrow().env_var()
+    }
+
+    fn is_repeatable(&self) -> bool {
+        self.0.borrow().is_repeatable()
+    }
+
+    fn is_trailing(&self) -> bool {
+        self.0.borrow().is_trailing()
+    }
+
+    fn is_named(&self, token: &str) -> bool {
+        self.0.borrow().is_named(token)
+    }
+
+    fn default_help(&self) -> Option<String> {
+        self.0.borrow().default_help()
+    }
+
+    fn constraints(&self) -> Option<String> {
+        self.0.borrow().constraints()
+    }
+
+    fn parses(&mut self, value: &str) -> bool {
+        self.0.borrow_mut().parses(value)
+    }
+
+    fn is_hidden(&self) -> bool {
+        self.0.borrow().is_hidden()
+    }
+
+    fn possible_values(&self) -> Vec<PossibleValue> {
+        self.0.borrow().possible_values()
+    }
+
+    fn complete(&mut self, value: &str) -> CliResult<Vec<String>> {
+        self.0.borrow_mut().complete(value)
+    }
+}
@@ -1,6 +1,15 @@
-use std::fmt::Display;
+use std::{
+    fmt::{Debug, Display},
+    ops::RangeBounds,
+    path::Path,
+    str::FromStr,
+};
 
-use crate::cli_error::CliResult;
+use crate::{
+    cli_error::{CliError, CliResult, ErrorKind, ErrorSource},
+    pattern::Pattern,
+    value_enum::PossibleValue,
+};
 
 /// parser complexities:
 ///
@@ -11,13 +20,65 @@ use crate::cli_error::CliResult;
 /// out of order flags
 pub trait Input {
     fn parsed(&self) -> bool;
+    /// whether parsing succeeds without this input, through a default or because it's optional
     fn has_default(&self) -> bool;
     fn parse(&mut self, token: &str) -> CliResult<bool>;
+    /// whether `token` names this input but leaves its value to the next token, `--key value`
+    fn expects_value(&self, token: &str) -> bool;
+    /// parse a bare value, one that isn't attached to a flag name
+    fn parse_value(&mut self, value: &str) -> CliResult<()>;
     fn display_name(&self) -> String;
+    /// the single character this input answers to as `-x`, if any
+    fn short_name(&self) -> Option<char>;
     fn description(&self) -> Option<String>;
     fn type_name(&self) -> InputType;
     fn is_bool_flag(&self) -> bool;
 
+    /// the environment variable read when this input is missing from the command line
+    fn env_var(&self) -> Option<String> {
+        None
+    }
+
+    /// whether this input keeps accepting values once it has one, like a list of trailing args
+    fn is_repeatable(&self) -> bool {
+        false
+    }
+
+    /// whether every token after this input's first value belongs to it, flag-like or not
+    fn is_trailing(&self) -> bool {
+        false
+    }
+
+    /// whether `token` is this flag's name in any of its forms, with or without a value
+    fn is_named(&self, _token: &str) -> bool {
+        false
+    }
+
+    /// the default value as help shows it, `[default: vi]`
+    fn default_help(&self) -> Option<String> {
+        None
+    }
+
+    /// what a value has to satisfy as help shows it, `<port: 1..=65535>`
+    fn constraints(&self) -> Option<String> {
+        None
+    }
+
+    /// whether `value` parses for this input, before any validators run, without keeping it
+    fn parses(&mut self, _value: &str) -> bool {
+        true
+    }
+
+    /// whether help and completions leave this input out, it's still parsed
+    fn is_hidden(&self) -> bool {
+        false
+    }
+
+    /// the only values this input accepts, for completions and help, empty when it takes any
+    fn possible_values(&self) -> Vec<PossibleValue> {
+        vec![]
+    }
+
     /// must not return completions that don't start with value, otherwise bash breaks
     fn complete(&mut self, value: &str) -> CliResult<Vec<String>>;
 }
@@ -30,6 +91,150 @@ pub enum InputType {
 
 pub type Completor<'a> = Box<dyn FnMut(&str) -> CliResult<Vec<String>> + 'a>;
 
+/// turns a token into an input's value, `FromStr` unless replaced with `.parser()`
+pub type Parser<'a, T> = Box<dyn FnMut(&str) -> CliResult<T> + 'a>;
+
+/// keeps a `FromStr` error as the source of the parse error it causes, set by `.parse_details()`
+/// on inputs that parse with `FromStr` directly
+pub type Details<E> = Option<fn(E) -> ErrorSource>;
+
+/// `value` parsed with `FromStr` for `input`, with the reason it failed when `details` is set
+pub(crate) fn from_str<T: FromStr>(
+    value: &str,
+    input: &str,
+    details: Details<T::Err>,
+) -> CliResult<T> {
+    value.parse().map_err(|err| match details {
+        Some(details) => CliError::unparsable_with(value, input, details(err)),
+        None => CliError::unparsable(value, input),
+    })
+}
+
+/// the value an input falls back on, computed each time it's needed so it can depend on the
+/// environment: `$EDITOR`, or `vi` when that's unset
+pub struct DefaultValue<'a, T> {
+    value: Box<dyn Fn() -> T + 'a>,
+    /// `None` for defaults too obvious to mention in help, like `false` for a bool flag
+    show: Option<fn(&T) -> String>,
+}
+
+impl<'a, T> DefaultValue<'a, T> {
+    pub fn new<F: Fn() -> T + 'a>(value: F) -> Self
+    where
+        T: Display,
+    {
+        Self {
+            value: Box::new(value),
+            show: Some(|value| value.to_string()),
+        }
+    }
+
+    pub(crate) fn hidden<F: Fn() -> T + 'a>(value: F) -> Self {
+        Self {
+            value: Box::new(value),
+            show: None,
+        }
+    }
+
+    /// lists the value in help from now on, `[default: vi]`
+    pub(crate) fn shown(mut self) -> Self
+    where
+        T: Display,
+    {
+        self.show = Some(|value| value.to_string());
+        self
+    }
+
+    pub fn get(&self) -> T {
+        (self.value)()
+    }
+
+    pub fn help(&self) -> Option<String> {
+        self.show.map(|show| show(&self.get()))
+    }
+}
+
+/// a check a parsed value has to pass before the handler sees it, with the constraint it
+/// enforces as help and errors describe it
+pub struct Validator<'a, T> {
+    pub description: String,
+    check: Box<dyn Fn(&T) -> bool + 'a>,
+}
+
+impl<'a, T> Validator<'a, T> {
+    pub fn new<F: Fn(&T) -> bool + 'a>(description: &str, check: F) -> Self {
+        Self {
+            description: description.to_string(),
+            check: Box::new(check),
+        }
+    }
+
+    /// values within `range`, described as the range is written: `1..=65535`
+    pub fn range<R>(range: R) -> Self
+    where
+        T: PartialOrd,
+        R: RangeBounds<T> + Debug + 'a,
+    {
+        Self {
+            description: format!("{range:?}"),
+            check: Box::new(move |value| range.contains(value)),
+        }
+    }
+
+    /// values matching `pattern`, see `pattern.rs` for the syntax it understands. Panics on a
+    /// malformed pattern, it's a mistake in the cli rather than in the user's input
+    pub fn pattern(pattern: &str) -> Self
+    where
+        T: AsRef<str>,
+    {
+        let matcher =
+            Pattern::new(pattern).unwrap_or_else(|err| panic!("invalid pattern {pattern}: {err}"));
+        Self {
+            description: format!("matching {pattern}"),
+            check: Box::new(move |value| matcher.is_match(value.as_ref())),
+        }
+    }
+
+    /// paths to a file or folder that exists
+    pub fn path_exists() -> Self
+    where
+        T: AsRef<Path>,
+    {
+        Self::new("an existing path", |value| value.as_ref().exists())
+    }
+
+    pub(crate) fn check(&self, value: &T, token: &str, input: &str) -> CliResult<()> {
+        if (self.check)(value) {
+            return Ok(());
+        }
+
+        Err(CliError::new(
+            ErrorKind::InvalidValue,
+            format!(
+                "{token} is not valid for {input}, expected {}",
+                self.description
+            ),
+        )
+        .token(token)
+        .input(input))
+    }
+}
+
+/// the descriptions of `validators` as help shows them, if there are any
+pub(crate) fn describe<T>(validators: &[Validator<T>]) -> Option<String> {
+    if validators.is_empty() {
+        return None;
+    }
+
+    Some(
+        validators
+            .iter()
+            .map(|validator| validator.description.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+    )
+}
+
 impl Display for InputType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
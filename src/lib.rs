@@ -1,6 +1,21 @@
+// lets derives used inside this crate, like `CompletionMode`'s, name it `::cli_rs`
+extern crate self as cli_rs;
+
+#[macro_use]
+mod builders;
+
 pub mod arg;
 pub mod cli_error;
 pub mod command;
+pub mod count_flag;
 pub mod flag;
+pub mod global;
 pub mod input;
+pub mod list_arg;
+pub mod list_flag;
+pub mod map_flag;
+pub mod opt_arg;
 pub mod parser;
+mod pattern;
+pub mod trailing_args;
+pub mod value_enum;
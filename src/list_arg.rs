@@ -0,0 +1,143 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    cli_error::{CliError, CliResult, ErrorKind, ErrorSource},
+    input::{self, Completor, Details, Input, InputType},
+};
+
+/// collects every remaining positional value, `lockbook rm a.md b.md c.md`. Must be the last
+/// positional input of a command, `.input()` panics otherwise.
+pub struct ListArg<'a, T: FromStr + Clone> {
+    pub name: String,
+    pub description: Option<String>,
+    pub values: Vec<T>,
+    pub completor: Option<Completor<'a>>,
+    /// keeps parse errors' reasons, set by `.parse_details()`
+    pub details: Details<T::Err>,
+}
+
+impl<'a, T> ListArg<'a, T>
+where
+    T: FromStr + Clone,
+{
+    pub fn name(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: None,
+            values: vec![],
+            completor: None,
+            details: None,
+        }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    pub fn get(&self) -> Vec<T> {
+        self.values.clone()
+    }
+
+    /// moves the values out rather than cloning them, leaving the list empty
+    pub fn take(&mut self) -> Vec<T> {
+        std::mem::take(&mut self.values)
+    }
+
+    pub fn completor<F>(mut self, completor: F) -> Self
+    where
+        F: FnMut(&str) -> CliResult<Vec<String>> + 'a,
+    {
+        self.completor = Some(Box::new(completor));
+        self
+    }
+}
+
+impl<'a, T> ListArg<'a, T>
+where
+    T: FromStr + Clone,
+    T::Err: Display + 'static,
+{
+    /// like `Arg::parse_details`, adds the reason `FromStr` gave to parse errors
+    pub fn parse_details(mut self) -> Self {
+        self.details = Some(ErrorSource::new);
+        self
+    }
+}
+
+impl<'a, T> Input for ListArg<'a, T>
+where
+    T: FromStr + Clone,
+{
+    fn parse(&mut self, token: &str) -> CliResult<bool> {
+        if token.len() > 2 && token.starts_with("--") {
+            return Err(CliError::new(
+                ErrorKind::UnknownFlag,
+                format!(
+                    "unexpected flag \"{}\" found while looking for argument \"{}\"",
+                    token, self.name
+                ),
+            )
+            .token(token)
+            .input(&self.name));
+        }
+        self.parse_value(token)?;
+
+        Ok(true)
+    }
+
+    fn expects_value(&self, _token: &str) -> bool {
+        false
+    }
+
+    fn parse_value(&mut self, value: &str) -> CliResult<()> {
+        self.values
+            .push(input::from_str(value, &self.name, self.details)?);
+
+        Ok(())
+    }
+
+    fn parses(&mut self, value: &str) -> bool {
+        value.parse::<T>().is_ok()
+    }
+
+    fn display_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn short_name(&self) -> Option<char> {
+        None
+    }
+
+    fn type_name(&self) -> InputType {
+        InputType::Arg
+    }
+
+    fn parsed(&self) -> bool {
+        !self.values.is_empty()
+    }
+
+    fn is_repeatable(&self) -> bool {
+        true
+    }
+
+    fn complete(&mut self, value: &str) -> CliResult<Vec<String>> {
+        if let Some(completor) = &mut self.completor {
+            completor(value)
+        } else {
+            Ok(vec![])
+        }
+    }
+
+    fn is_bool_flag(&self) -> bool {
+        false
+    }
+
+    fn description(&self) -> Option<String> {
+        self.description.clone()
+    }
+
+    fn has_default(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,148 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    cli_error::{CliResult, ErrorSource},
+    flag::split_token,
+    input::{self, Completor, Details, Input, InputType},
+};
+
+/// a flag that may be given any number of times, collecting every value:
+/// `--tag work --tag urgent` or `--tag work,urgent`
+pub struct ListFlag<'a, T: FromStr + Clone> {
+    pub name: String,
+    pub description: Option<String>,
+    pub values: Vec<T>,
+    pub short: Option<char>,
+    pub completor: Option<Completor<'a>>,
+    pub env: Option<String>,
+    pub hidden: bool,
+    /// keeps parse errors' reasons, set by `.parse_details()`
+    pub details: Details<T::Err>,
+}
+
+impl<'a, T: FromStr + Clone> ListFlag<'a, T> {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: None,
+            values: vec![],
+            short: name.chars().next(),
+            completor: None,
+            env: None,
+            hidden: false,
+            details: None,
+        }
+    }
+
+    pub fn get(&self) -> Vec<T> {
+        self.values.clone()
+    }
+
+    flag_builders!();
+
+    /// read comma separated values from `var` when none are given on the command line
+    pub fn env(mut self, var: &str) -> Self {
+        self.env = Some(var.to_string());
+        self
+    }
+
+    pub fn completor<F>(mut self, completor: F) -> Self
+    where
+        F: FnMut(&str) -> CliResult<Vec<String>> + 'a,
+    {
+        self.completor = Some(Box::new(completor));
+        self
+    }
+}
+
+impl<'a, T> ListFlag<'a, T>
+where
+    T: FromStr + Clone,
+    T::Err: Display + 'static,
+{
+    /// like `Arg::parse_details`, adds the reason `FromStr` gave to parse errors
+    pub fn parse_details(mut self) -> Self {
+        self.details = Some(ErrorSource::new);
+        self
+    }
+}
+
+impl<'a, T> Input for ListFlag<'a, T>
+where
+    T: FromStr + Clone,
+{
+    fn parse(&mut self, token: &str) -> CliResult<bool> {
+        match split_token(&self.name, self.short, token) {
+            Some(Some(value)) => {
+                self.parse_value(value)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn expects_value(&self, token: &str) -> bool {
+        split_token(&self.name, self.short, token) == Some(None)
+    }
+
+    fn is_named(&self, token: &str) -> bool {
+        split_token(&self.name, self.short, token).is_some()
+    }
+
+    fn parse_value(&mut self, value: &str) -> CliResult<()> {
+        for value in value.split(',') {
+            self.values
+                .push(input::from_str(value, &self.name, self.details)?);
+        }
+
+        Ok(())
+    }
+
+    fn display_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn short_name(&self) -> Option<char> {
+        self.short
+    }
+
+    fn type_name(&self) -> InputType {
+        InputType::Flag
+    }
+
+    fn parsed(&self) -> bool {
+        !self.values.is_empty()
+    }
+
+    fn is_repeatable(&self) -> bool {
+        true
+    }
+
+    fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    fn complete(&mut self, value: &str) -> CliResult<Vec<String>> {
+        if let Some(completor) = &mut self.completor {
+            completor(value)
+        } else {
+            Ok(vec![])
+        }
+    }
+
+    fn is_bool_flag(&self) -> bool {
+        false
+    }
+
+    fn env_var(&self) -> Option<String> {
+        self.env.clone()
+    }
+
+    fn description(&self) -> Option<String> {
+        self.description.clone()
+    }
+
+    fn has_default(&self) -> bool {
+        true
+    }
+}
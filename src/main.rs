@@ -1,4 +1,19 @@
-use cli_rs::{arg::Arg, cli_error::Exit, command::Command, flag::Flag, parser::Cmd};
+use cli_rs::{
+    arg::Arg, cli_error::Exit, command::Command, flag::Flag, parser::Cmd, value_enum::ValueEnum,
+};
+
+#[derive(ValueEnum, Clone, Debug)]
+#[value(display)]
+enum Editor {
+    Vim,
+    /// neovim
+    Nvim,
+    Nano,
+    Sublime,
+    /// visual studio code
+    #[value(alias = "vscode")]
+    Code,
+}
 
 fn main() {
     let files = vec![
@@ -13,13 +28,11 @@ fn main() {
     Command::name("cli-rs")
         .subcommand(
             Command::name("edit")
-                .input(Flag::<String>::new("editor").completor(|prompt| {
-                    Ok(["vim", "nvim", "nano", "sublime", "code"]
-                        .iter()
-                        .filter(|editor| editor.starts_with(prompt))
-                        .map(|s| s.to_string())
-                        .collect())
-                }))
+                .input(
+                    Flag::<Editor>::value_enum("editor")
+                        .default(Editor::Vim)
+                        .show_default(),
+                )
                 .input(Flag::<String>::new("force"))
                 .input(Arg::<String>::name("target").completor(|prompt| {
                     Ok(files
@@ -28,8 +41,12 @@ fn main() {
                         .filter(|file| file.starts_with(prompt))
                         .collect())
                 }))
-                .handler(|_, _, target| {
-                    println!("editing target file: {}", target.get());
+                .handler(|editor, _, target| {
+                    println!(
+                        "editing target file: {} with {}",
+                        target.get(),
+                        editor.get()
+                    );
                     Ok(())
                 }),
         )
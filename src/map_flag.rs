@@ -0,0 +1,209 @@
+use std::{collections::HashMap, fmt::Display, hash::Hash, str::FromStr};
+
+use crate::{
+    cli_error::{CliError, CliResult, ErrorKind, ErrorSource},
+    flag::split_token,
+    input::{Completor, Details, Input, InputType},
+};
+
+/// completes a map value, given its key and the value typed so far
+pub type ValueCompletor<'a> = Box<dyn FnMut(&str, &str) -> CliResult<Vec<String>> + 'a>;
+
+/// a flag collecting `key=value` pairs, `-D editor=vim --define theme=dark`. A key given twice
+/// keeps its last value.
+pub struct MapFlag<'a, K, V>
+where
+    K: FromStr + Eq + Hash + Clone,
+    V: FromStr + Clone,
+{
+    pub name: String,
+    pub description: Option<String>,
+    pub values: HashMap<K, V>,
+    pub short: Option<char>,
+    pub key_completor: Option<Completor<'a>>,
+    pub value_completor: Option<ValueCompletor<'a>>,
+    pub hidden: bool,
+    /// keeps parse errors' reasons, set by `.parse_details()`
+    pub key_details: Details<K::Err>,
+    pub value_details: Details<V::Err>,
+}
+
+impl<'a, K, V> MapFlag<'a, K, V>
+where
+    K: FromStr + Eq + Hash + Clone,
+    V: FromStr + Clone,
+{
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: None,
+            values: HashMap::new(),
+            short: name.chars().next(),
+            key_completor: None,
+            value_completor: None,
+            hidden: false,
+            key_details: None,
+            value_details: None,
+        }
+    }
+
+    pub fn get(&self) -> HashMap<K, V> {
+        self.values.clone()
+    }
+
+    flag_builders!();
+
+    /// completes the part before the `=`
+    pub fn key_completor<F>(mut self, completor: F) -> Self
+    where
+        F: FnMut(&str) -> CliResult<Vec<String>> + 'a,
+    {
+        self.key_completor = Some(Box::new(completor));
+        self
+    }
+
+    /// completes the part after the `=`, given the key it belongs to and the value typed so far
+    pub fn value_completor<F>(mut self, completor: F) -> Self
+    where
+        F: FnMut(&str, &str) -> CliResult<Vec<String>> + 'a,
+    {
+        self.value_completor = Some(Box::new(completor));
+        self
+    }
+
+    /// an error about `pair`, with the reason its key or value failed to parse when there is one
+    fn invalid(&self, pair: &str, mut problem: String, source: Option<ErrorSource>) -> CliError {
+        if let Some(source) = &source {
+            problem = format!("{problem}: {source}");
+        }
+
+        let mut err = CliError::new(ErrorKind::InvalidValue, problem)
+            .token(pair)
+            .input(&self.name);
+        err.source = source;
+        err
+    }
+}
+
+impl<'a, K, V> MapFlag<'a, K, V>
+where
+    K: FromStr + Eq + Hash + Clone,
+    V: FromStr + Clone,
+    K::Err: Display + 'static,
+    V::Err: Display + 'static,
+{
+    /// like `Arg::parse_details`, adds the reason `FromStr` gave to a bad key or value
+    pub fn parse_details(mut self) -> Self {
+        self.key_details = Some(ErrorSource::new);
+        self.value_details = Some(ErrorSource::new);
+        self
+    }
+}
+
+impl<'a, K, V> Input for MapFlag<'a, K, V>
+where
+    K: FromStr + Eq + Hash + Clone,
+    V: FromStr + Clone,
+{
+    fn parse(&mut self, token: &str) -> CliResult<bool> {
+        match split_token(&self.name, self.short, token) {
+            Some(Some(pair)) => {
+                self.parse_value(pair)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn expects_value(&self, token: &str) -> bool {
+        split_token(&self.name, self.short, token) == Some(None)
+    }
+
+    fn is_named(&self, token: &str) -> bool {
+        split_token(&self.name, self.short, token).is_some()
+    }
+
+    fn parse_value(&mut self, pair: &str) -> CliResult<()> {
+        let Some((key, value)) = pair.split_once('=') else {
+            let problem = format!("{pair} should be a key=value pair for {}", self.name);
+            return Err(self.invalid(pair, problem, None));
+        };
+
+        let key = key.parse().map_err(|err| {
+            let problem = format!(
+                "{key} cannot be parsed as a key for {} in {pair}",
+                self.name
+            );
+            self.invalid(pair, problem, self.key_details.map(|details| details(err)))
+        })?;
+        let value = value.parse().map_err(|err| {
+            let problem = format!(
+                "{value} cannot be parsed as a value for {} in {pair}",
+                self.name
+            );
+            self.invalid(
+                pair,
+                problem,
+                self.value_details.map(|details| details(err)),
+            )
+        })?;
+
+        self.values.insert(key, value);
+        Ok(())
+    }
+
+    fn display_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn short_name(&self) -> Option<char> {
+        self.short
+    }
+
+    fn type_name(&self) -> InputType {
+        InputType::Flag
+    }
+
+    fn parsed(&self) -> bool {
+        !self.values.is_empty()
+    }
+
+    fn is_repeatable(&self) -> bool {
+        true
+    }
+
+    fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    fn complete(&mut self, value: &str) -> CliResult<Vec<String>> {
+        match value.split_once('=') {
+            Some((key, value)) => match &mut self.value_completor {
+                Some(completor) => Ok(completor(key, value)?
+                    .into_iter()
+                    .map(|value| format!("{key}={value}"))
+                    .collect()),
+                None => Ok(vec![]),
+            },
+            None => match &mut self.key_completor {
+                Some(completor) => Ok(completor(value)?
+                    .into_iter()
+                    .map(|key| format!("{key}="))
+                    .collect()),
+                None => Ok(vec![]),
+            },
+        }
+    }
+
+    fn is_bool_flag(&self) -> bool {
+        false
+    }
+
+    fn description(&self) -> Option<String> {
+        self.description.clone()
+    }
+
+    fn has_default(&self) -> bool {
+        true
+    }
+}
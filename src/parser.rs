@@ -1,10 +1,11 @@
-use std::{env, fmt::Write};
+use std::{collections::HashMap, env, fmt::Write};
 
 use crate::{
-    cli_error::{CliError, CliResult},
-    command::{CompletionMode, ParserInfo},
+    cli_error::{CliError, CliResult, ErrorKind},
+    command::{check_rule_inputs, CompletionMode, DocInfo, ParserInfo, Rule},
     flag::Flag,
     input::{Input, InputType},
+    value_enum,
 };
 
 use colored::*;
@@ -24,8 +25,555 @@ fn help_flag() -> Flag<'static, bool> {
     Flag::bool("help").description("view help")
 }
 
+/// offers a flag-like token to every unparsed flag, returning how many tokens were consumed: 2 if
+/// the flag took the following token as its value
+fn parse_flag(
+    symbols: &mut [&mut dyn Input],
+    token: &str,
+    next: Option<&String>,
+) -> CliResult<usize> {
+    for symbol in symbols.iter_mut() {
+        if (symbol.parsed() && !symbol.is_repeatable()) || symbol.type_name() != InputType::Flag {
+            continue;
+        }
+
+        if symbol.parse(token)? {
+            return Ok(1);
+        }
+
+        if symbol.expects_value(token) {
+            let value = next.ok_or_else(|| {
+                let name = symbol.display_name();
+                CliError::new(ErrorKind::MissingValue, format!("Missing value for {name}"))
+                    .token(token)
+                    .input(&name)
+            })?;
+            symbol.parse_value(value)?;
+            return Ok(2);
+        }
+    }
+
+    let mut msg = format!("Unexpected flag-like token found {token}");
+    if let Some(name) = token.strip_prefix("--") {
+        let name = name.split('=').next().unwrap_or(name);
+        let flags = symbols
+            .iter()
+            .filter(|sym| sym.type_name() == InputType::Flag && !sym.is_hidden())
+            .map(|sym| sym.display_name())
+            .collect::<Vec<_>>();
+        if let Some(flag) = closest(name, &flags) {
+            write!(msg, ", did you mean `--{flag}`?").unwrap();
+        }
+    }
+
+    Err(CliError::new(ErrorKind::UnknownFlag, msg).token(token))
+}
+
+/// the number of single character insertions, deletions and substitutions turning `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != *b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// the candidate `token` was most likely a typo of, if any is close enough to be worth suggesting
+fn closest<'c>(token: &str, candidates: &'c [String]) -> Option<&'c String> {
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(token, candidate), candidate))
+        .filter(|(distance, candidate)| {
+            // 0 means the token was valid but given again, and a distance as long as the
+            // candidate itself means nothing about them matched
+            *distance > 0
+                && *distance <= (candidate.len() / 3).max(2)
+                && *distance < candidate.len()
+        })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// whether `token` reads as a negative number, `-5` or `-0.5`, rather than a flag
+fn is_negative_number(token: &str) -> bool {
+    token
+        .strip_prefix('-')
+        .filter(|number| number.starts_with(|c: char| c.is_ascii_digit() || c == '.'))
+        .is_some_and(|number| number.parse::<f64>().is_ok())
+}
+
+/// whether a flag answers to every character of a short flag or bundle, `-15` needs both `-1`
+/// and `-5`
+fn shorts_known(symbols: &[&mut dyn Input], token: &str) -> bool {
+    let shorts = token.strip_prefix('-').unwrap_or(token);
+    let shorts = shorts.split_once('=').map_or(shorts, |(shorts, _)| shorts);
+    shorts.chars().all(|short| {
+        symbols
+            .iter()
+            .any(|sym| sym.type_name() == InputType::Flag && sym.short_name() == Some(short))
+    })
+}
+
+/// the positional input the next positional token goes to
+fn next_positional<'s, 'i>(
+    symbols: &'s mut [&'i mut dyn Input],
+) -> Option<&'s mut &'i mut dyn Input> {
+    symbols
+        .iter_mut()
+        .find(|sym| sym.type_name() == InputType::Arg && (!sym.parsed() || sym.is_repeatable()))
+}
+
+/// splits a bundle of short flags such as `-rfo=out.txt` into its flag characters and the value
+/// attached to the last of them
+fn short_bundle(token: &str) -> Option<(Vec<char>, Option<&str>)> {
+    if token.starts_with("--") {
+        return None;
+    }
+
+    let token = token.strip_prefix('-')?;
+    let (shorts, value) = match token.split_once('=') {
+        Some((shorts, value)) => (shorts, Some(value)),
+        None => (token, None),
+    };
+
+    let shorts: Vec<char> = shorts.chars().collect();
+    (shorts.len() > 1).then_some((shorts, value))
+}
+
+/// parses every flag in a bundle, only the last flag may take a value
+fn parse_bundle(
+    symbols: &mut [&mut dyn Input],
+    token: &str,
+    shorts: &[char],
+    value: Option<&str>,
+    next: Option<&String>,
+) -> CliResult<usize> {
+    let mut consumed = 1;
+    for (i, short) in shorts.iter().enumerate() {
+        let last = i == shorts.len() - 1;
+        let flag = symbols
+            .iter()
+            .find(|sym| sym.type_name() == InputType::Flag && sym.short_name() == Some(*short));
+
+        match flag {
+            None => {
+                return Err(CliError::new(
+                    ErrorKind::UnknownFlag,
+                    format!("Unknown short flag -{short} in {token}"),
+                )
+                .token(token))
+            }
+            Some(flag) if !last && !flag.is_bool_flag() => {
+                return Err(CliError::new(
+                    ErrorKind::MissingValue,
+                    format!("-{short} takes a value, so it must come last in {token}"),
+                )
+                .token(token)
+                .input(&flag.display_name()))
+            }
+            _ => {}
+        }
+
+        if last {
+            let single = match value {
+                Some(value) => format!("-{short}={value}"),
+                None => format!("-{short}"),
+            };
+            consumed = parse_flag(symbols, &single, next)?;
+        } else {
+            parse_flag(symbols, &format!("-{short}"), None)?;
+        }
+    }
+
+    Ok(consumed)
+}
+
+/// the index of the flag that will take the token following `token` as its value, if any
+fn awaiting_value(symbols: &[&mut dyn Input], token: &str) -> Option<usize> {
+    let token = match short_bundle(token) {
+        Some((_, Some(_))) => return None,
+        Some((shorts, None)) => format!("-{}", shorts[shorts.len() - 1]),
+        None => token.to_string(),
+    };
+
+    symbols
+        .iter()
+        .position(|sym| sym.type_name() == InputType::Flag && sym.expects_value(&token))
+}
+
+/// how a positional input is written in usage lines: `<target>`, `[target]`, `<files>...` or
+/// `[-- <args>...]`
+fn arg_usage(symbol: &dyn Input) -> String {
+    let mut name = symbol.display_name();
+    if let Some(constraints) = symbol.constraints() {
+        name = format!("{name}: {constraints}");
+    }
+    if symbol.is_trailing() {
+        format!("[-- <{name}>...]")
+    } else if symbol.is_repeatable() {
+        format!("<{name}>...")
+    } else if symbol.has_default() {
+        format!("[{name}]")
+    } else {
+        format!("<{name}>")
+    }
+}
+
+/// `symbol`'s completions for `prompt`, described when they're one of its possible values
+fn value_completions(symbol: &mut dyn Input, prompt: &str) -> CliResult<Vec<CompOut>> {
+    let possible_values = symbol.possible_values();
+    let completions = symbol.complete(prompt)?.into_iter().map(|name| {
+        let desc = possible_values
+            .iter()
+            .find(|value| value.name == name)
+            .and_then(|value| value.description.clone());
+        CompOut { name, desc }
+    });
+
+    Ok(completions.collect())
+}
+
+/// how usage shows a flag that has to be given, `--host <host>`
+fn flag_usage(symbol: &dyn Input) -> String {
+    let name = symbol.display_name();
+    if symbol.is_bool_flag() {
+        format!("--{name}")
+    } else {
+        format!("--{name} <{name}>")
+    }
+}
+
+/// lists every input of kind `input_type` under `title`, padding names to `width`
+fn write_inputs(
+    help_message: &mut String,
+    title: &str,
+    symbols: &[&mut dyn Input],
+    input_type: InputType,
+    width: usize,
+) {
+    writeln!(help_message, "\n{}", title.yellow().bold()).unwrap();
+    for symbol in symbols {
+        if symbol.type_name() != input_type || symbol.is_hidden() {
+            continue;
+        }
+
+        if input_type == InputType::Flag {
+            write!(help_message, "\t--{:width$}", symbol.display_name().bold()).unwrap();
+        } else {
+            write!(help_message, "\t{:width$}", arg_usage(*symbol).bold()).unwrap();
+        }
+        if let Some(desc) = symbol.description() {
+            write!(help_message, " {desc}").unwrap();
+        }
+        if let Some(var) = symbol.env_var() {
+            write!(help_message, " [env: {var}]").unwrap();
+        }
+        if input_type == InputType::Flag && !symbol.has_default() {
+            write!(help_message, " [required]").unwrap();
+        }
+        if let Some(default) = symbol.default_help() {
+            write!(help_message, " [default: {default}]").unwrap();
+        }
+        let possible_values = symbol.possible_values();
+        if !possible_values.is_empty() {
+            write!(
+                help_message,
+                " [possible values: {}]",
+                value_enum::list(&possible_values)
+            )
+            .unwrap();
+        }
+        if input_type == InputType::Flag {
+            if let Some(constraints) = symbol.constraints() {
+                write!(help_message, " [expected: {constraints}]").unwrap();
+            }
+        }
+        writeln!(help_message).unwrap();
+    }
+}
+
+/// `--verb=3` as `--verbose=3` when `verb` starts exactly one visible flag's long name, for
+/// commands that infer prefixes. Whole names and prefixes of nothing are left alone, the latter
+/// fail as unknown flags.
+fn expand_flag_prefix(symbols: &[&mut dyn Input], token: &str) -> CliResult<Option<String>> {
+    let Some(given) = token.strip_prefix("--") else {
+        return Ok(None);
+    };
+    let (prefix, value) = match given.split_once('=') {
+        Some((prefix, value)) => (prefix, Some(value)),
+        None => (given, None),
+    };
+    if prefix.is_empty() {
+        return Ok(None);
+    }
+
+    // with whether they're hidden, a hidden flag is only given by its whole name
+    let mut names = vec![];
+    for flag in symbols
+        .iter()
+        .filter(|sym| sym.type_name() == InputType::Flag)
+    {
+        let name = flag.display_name();
+        if flag.is_named(&format!("--no-{name}")) {
+            names.push((format!("no-{name}"), flag.is_hidden()));
+        }
+        names.push((name, flag.is_hidden()));
+    }
+    if names.iter().any(|(name, _)| name == prefix) {
+        return Ok(None);
+    }
+
+    let candidates = names
+        .iter()
+        .filter(|(name, hidden)| !hidden && name.starts_with(prefix))
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+
+    match candidates[..] {
+        [] => Ok(None),
+        [name] => Ok(Some(match value {
+            Some(value) => format!("--{name}={value}"),
+            None => format!("--{name}"),
+        })),
+        _ => {
+            let candidates = candidates.iter().map(|name| format!("--{name}"));
+            let candidates = candidates.collect::<Vec<_>>().join(", ");
+            let msg = format!("--{prefix} is ambiguous, it could be {candidates}");
+            Err(CliError::new(ErrorKind::AmbiguousPrefix, msg).token(token))
+        }
+    }
+}
+
+/// the subcommand `token` names, or for commands that infer prefixes, the only visible one whose
+/// name or an alias starts with `token`. When several do, `Err` holds their names.
+fn find_subcommand(
+    subcommands: &[DocInfo],
+    token: &str,
+    infer_prefixes: bool,
+) -> Result<Option<usize>, Vec<String>> {
+    if let Some(idx) = subcommands.iter().position(|sub| sub.answers_to(token)) {
+        return Ok(Some(idx));
+    }
+    if !infer_prefixes {
+        return Ok(None);
+    }
+
+    let candidates = subcommands
+        .iter()
+        .enumerate()
+        .filter(|(_, sub)| {
+            !sub.hidden
+                && (sub.name.starts_with(token)
+                    || sub.aliases.iter().any(|alias| alias.starts_with(token)))
+        })
+        .collect::<Vec<_>>();
+
+    match candidates[..] {
+        [] => Ok(None),
+        [(idx, _)] => Ok(Some(idx)),
+        _ => Err(candidates
+            .into_iter()
+            .map(|(_, sub)| sub.name.clone())
+            .collect()),
+    }
+}
+
+/// how many of `tokens` this command reads its own flags from, the rest come after `--`, go to the
+/// subcommand at `sub_pos` or to a trailing input
+fn own_flags_end(symbols: &[&mut dyn Input], tokens: &[String], sub_pos: Option<usize>) -> usize {
+    let trailing_at = symbols
+        .iter()
+        .filter(|sym| sym.type_name() == InputType::Arg)
+        .position(|sym| sym.is_trailing());
+
+    let mut positionals = 0;
+    let mut idx = 0;
+    while idx < tokens.len() {
+        let token = &tokens[idx];
+        if token == "--" || sub_pos == Some(idx) {
+            return idx;
+        }
+
+        if token.starts_with('-') && !is_negative_number(token) {
+            let takes_next = awaiting_value(symbols, token).is_some()
+                && tokens
+                    .get(idx + 1)
+                    .is_some_and(|next| !next.starts_with('-'));
+            idx += if takes_next { 2 } else { 1 };
+        } else {
+            if trailing_at == Some(positionals) {
+                return idx;
+            }
+            positionals += 1;
+            idx += 1;
+        }
+    }
+
+    tokens.len()
+}
+
+/// position of the subcommand name in `tokens`, skipping over flags (and their values) given
+/// ahead of it
+fn subcommand_position(
+    symbols: &[&mut dyn Input],
+    tokens: &[String],
+    infer_prefixes: bool,
+) -> Option<usize> {
+    let mut idx = 0;
+    while idx < tokens.len() {
+        let mut token = tokens[idx].clone();
+        if !token.starts_with('-') {
+            return Some(idx);
+        }
+        if infer_prefixes {
+            if let Ok(Some(expanded)) = expand_flag_prefix(symbols, &token) {
+                token = expanded;
+            }
+        }
+
+        idx += if awaiting_value(symbols, &token).is_some() {
+            2
+        } else {
+            1
+        };
+    }
+
+    None
+}
+
+/// how errors and help refer to an input, `--force` or `<target>`
+fn input_ref(symbol: &dyn Input) -> String {
+    match symbol.type_name() {
+        InputType::Flag => format!("--{}", symbol.display_name()),
+        InputType::Arg => format!("<{}>", symbol.display_name()),
+    }
+}
+
+/// the input a rule names, which the command checked it has before parsing
+fn rule_input<'s>(symbols: &'s [&mut dyn Input], name: &str, cmd_path: &str) -> &'s dyn Input {
+    match symbols.iter().find(|symbol| symbol.display_name() == name) {
+        Some(symbol) => &**symbol,
+        None => panic!("{cmd_path}: a rule names {name}, which isn't one of its inputs"),
+    }
+}
+
+/// a rule as help lists it, in the words its error uses
+fn describe_rule(rule: &Rule, symbols: &[&mut dyn Input], cmd_path: &str) -> String {
+    let name = |name: &str| input_ref(rule_input(symbols, name, cmd_path));
+    match rule {
+        Rule::Conflicts(input, other) => {
+            format!("{} cannot be used with {}", name(input), name(other))
+        }
+        Rule::Requires(input, required) => format!("{} requires {}", name(input), name(required)),
+        Rule::ExactlyOne(inputs) => {
+            let inputs = inputs.iter().map(|input| name(input)).collect::<Vec<_>>();
+            format!("exactly one of {}", inputs.join(", "))
+        }
+    }
+}
+
+fn conflict(input: &dyn Input, other: &dyn Input) -> CliError {
+    CliError::new(
+        ErrorKind::ArgumentConflict,
+        format!(
+            "{} cannot be used with {}",
+            input_ref(input),
+            input_ref(other)
+        ),
+    )
+    .input(&input.display_name())
+}
+
+/// the first of `rules` the parsed `symbols` break, as an error naming the inputs involved
+fn check_rules(rules: &[Rule], symbols: &[&mut dyn Input], cmd_path: &str) -> CliResult<()> {
+    let find = |name: &str| rule_input(symbols, name, cmd_path);
+    for rule in rules {
+        match rule {
+            Rule::Conflicts(input, other) => {
+                let (input, other) = (find(input), find(other));
+                if input.parsed() && other.parsed() {
+                    return Err(conflict(input, other));
+                }
+            }
+            Rule::Requires(input, required) => {
+                let (input, required) = (find(input), find(required));
+                if input.parsed() && !required.parsed() {
+                    let msg = format!("{} requires {}", input_ref(input), input_ref(required));
+                    return Err(CliError::new(ErrorKind::MissingArgument, msg)
+                        .input(&required.display_name()));
+                }
+            }
+            Rule::ExactlyOne(inputs) => {
+                let inputs = inputs.iter().map(|input| find(input)).collect::<Vec<_>>();
+                let given = inputs
+                    .iter()
+                    .filter(|input| input.parsed())
+                    .collect::<Vec<_>>();
+                match given[..] {
+                    [] => {
+                        let names = inputs.iter().map(|input| input_ref(*input));
+                        let msg = format!(
+                            "one of {} is required",
+                            names.collect::<Vec<_>>().join(", ")
+                        );
+                        return Err(CliError::new(ErrorKind::MissingArgument, msg));
+                    }
+                    [input, other, ..] => return Err(conflict(*input, *other)),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// the flags `rules` keep from being given alongside those in `used`, by index into `symbols`
+fn conflicting(rules: &[Rule], symbols: &[&mut dyn Input], used: &[usize]) -> Vec<usize> {
+    let position = |name: &String| symbols.iter().position(|sym| &sym.display_name() == name);
+    let mut conflicting = vec![];
+    for rule in rules {
+        let group = match rule {
+            Rule::Conflicts(input, other) => {
+                [input, other].into_iter().filter_map(position).collect()
+            }
+            Rule::ExactlyOne(inputs) => inputs.iter().filter_map(position).collect(),
+            Rule::Requires(..) => vec![],
+        };
+
+        if group.iter().any(|i| used.contains(i)) {
+            conflicting.extend(group.into_iter().filter(|i| !used.contains(i)));
+        }
+    }
+
+    conflicting
+}
+
+/// the tokens a subcommand sees: everything but its own name, including global flags given
+/// before it
+fn forwarded_tokens(tokens: &[String], sub_pos: usize) -> Vec<String> {
+    tokens[..sub_pos]
+        .iter()
+        .chain(&tokens[sub_pos + 1..])
+        .cloned()
+        .collect()
+}
+
 pub trait Cmd: ParserInfo {
-    fn gen_help(&mut self) -> CliError {
+    fn gen_help(&mut self) -> String {
+        self.gen_help_inherited(vec![])
+    }
+
+    /// help for this command, also listing the global flags declared by its parents
+    fn gen_help_inherited(&mut self, inherited: Vec<&mut dyn Input>) -> String {
         let cmd_path = self.docs().cmd_path();
         let mut help_message = String::new();
 
@@ -37,53 +585,83 @@ pub trait Cmd: ParserInfo {
 
         writeln!(help_message, "\n").unwrap();
 
+        let has_version = self.docs().version.is_some();
+        let negative_numbers = self.docs().negative_numbers;
+        let rules = self.docs().all_rules();
+        let subcommands = self.subcommand_docs();
+
         let mut version = version_flag();
         let mut help = help_flag();
-        let mut built_in: Vec<&mut dyn Input> = vec![&mut help];
-        if self.docs().version.is_some() {
-            built_in.push(&mut version);
+        let mut symbols = self.symbols();
+        symbols.extend(inherited.into_iter().map(|g| g as &mut dyn Input));
+        symbols.push(&mut help);
+        if has_version {
+            symbols.push(&mut version);
         }
-        let subcommands = self.subcommand_docs();
-        writeln!(help_message, "{}", "USAGE:".bold().yellow()).unwrap();
-        if subcommands.is_empty() {
-            let usage = format!("{cmd_path} [options], <args>").bold();
-            writeln!(help_message, "\t{usage}").unwrap();
-            writeln!(help_message, "\n{}", "FLAGS:".yellow().bold()).unwrap();
 
-            let width = self
-                .symbols()
-                .into_iter()
-                .map(|s| s.display_name().len() + 3)
-                .chain([10]) // --version
-                .max()
-                .unwrap();
+        let width = symbols
+            .iter()
+            .filter(|s| !s.is_hidden())
+            .map(|s| s.display_name().len() + 5) // <files>...
+            .max()
+            .unwrap();
 
-            for symbol in self.symbols().iter().chain(built_in.iter()) {
-                if symbol.type_name() == InputType::Flag {
-                    write!(help_message, "\t--{:width$}", symbol.display_name().bold()).unwrap();
-                    if let Some(desc) = symbol.description() {
-                        write!(help_message, " {desc}").unwrap();
-                    }
-                    writeln!(help_message).unwrap();
+        writeln!(help_message, "{}", "USAGE:".bold().yellow()).unwrap();
+        if subcommands.is_empty() {
+            let mut usage = cmd_path.clone();
+            for symbol in &symbols {
+                let required = !symbol.has_default() && !symbol.is_hidden();
+                if symbol.type_name() == InputType::Flag && required {
+                    write!(usage, " {}", flag_usage(*symbol)).unwrap();
                 }
             }
-            writeln!(help_message, "\n{}", "ARGS:".yellow().bold()).unwrap();
-            for symbol in self.symbols() {
+            write!(usage, " [options]").unwrap();
+            for symbol in &symbols {
                 if symbol.type_name() == InputType::Arg {
-                    write!(help_message, "\t{:width$}", symbol.display_name().bold()).unwrap();
-                    if let Some(desc) = symbol.description() {
-                        write!(help_message, " {desc}").unwrap();
-                    }
-                    writeln!(help_message).unwrap();
+                    write!(usage, " {}", arg_usage(*symbol)).unwrap();
+                }
+            }
+            let usage = usage.bold();
+            writeln!(help_message, "\t{usage}").unwrap();
+
+            write_inputs(
+                &mut help_message,
+                "FLAGS:",
+                &symbols,
+                InputType::Flag,
+                width,
+            );
+            write_inputs(&mut help_message, "ARGS:", &symbols, InputType::Arg, width);
+            if !rules.is_empty() {
+                writeln!(help_message, "\n{}", "RULES:".yellow().bold()).unwrap();
+                for rule in &rules {
+                    let rule = describe_rule(rule, &symbols, &cmd_path);
+                    writeln!(help_message, "\t{rule}").unwrap();
                 }
             }
+            if negative_numbers {
+                writeln!(
+                    help_message,
+                    "\n\tnegative numbers such as -5 are read as args, not flags"
+                )
+                .unwrap();
+            }
         } else {
             let usage = format! {"{cmd_path} <subcommand>"}.bold();
             writeln!(help_message, "\t{usage}").unwrap();
             writeln!(help_message, "\n{}", "SUBCOMMANDS:".yellow().bold()).unwrap();
-            let sub_width = subcommands.iter().map(|s| s.name.len()).max().unwrap();
-            for subcommand in subcommands {
-                write!(help_message, "\t{:sub_width$}", subcommand.name.bold()).unwrap();
+            let subcommands = subcommands
+                .into_iter()
+                .filter(|sub| !sub.hidden)
+                .map(|sub| match &sub.aliases[..] {
+                    [] => (sub.name.clone(), sub),
+                    aliases => (format!("{} ({})", sub.name, aliases.join(", ")), sub),
+                })
+                .collect::<Vec<_>>();
+            let sub_width = subcommands.iter().map(|(name, _)| name.len()).max();
+            let sub_width = sub_width.unwrap_or_default();
+            for (name, subcommand) in subcommands {
+                write!(help_message, "\t{:sub_width$}", name.bold()).unwrap();
                 if let Some(description) = subcommand.description {
                     write!(help_message, " {description}").unwrap();
                 }
@@ -91,31 +669,16 @@ pub trait Cmd: ParserInfo {
                 writeln!(help_message).unwrap();
             }
 
-            writeln!(help_message, "\n{}", "FLAGS:".yellow().bold()).unwrap();
-
-            let flag_width = built_in
-                .iter()
-                .map(|s| s.display_name().len() + 3)
-                .max()
-                .unwrap();
-
-            for symbol in built_in {
-                if symbol.type_name() == InputType::Flag {
-                    write!(
-                        help_message,
-                        "\t--{:flag_width$}",
-                        symbol.display_name().bold()
-                    )
-                    .unwrap();
-                    if let Some(desc) = symbol.description() {
-                        write!(help_message, " {desc}").unwrap();
-                    }
-                    writeln!(help_message).unwrap();
-                }
-            }
+            write_inputs(
+                &mut help_message,
+                "FLAGS:",
+                &symbols,
+                InputType::Flag,
+                width,
+            );
         }
 
-        CliError::from(help_message)
+        help_message
     }
 
     // split this out into a trait that is pub, make the rest not pub
@@ -125,7 +688,7 @@ pub trait Cmd: ParserInfo {
         if args.len() >= 5 && args[1] == "complete" {
             let name = self.docs().name.to_string();
             // going to need some serious tests here
-            let shell = args[2].parse::<CompletionMode>().unwrap();
+            let shell = args[2].parse::<CompletionMode>()?;
             let prompt: Vec<String> = if shell == CompletionMode::Fish {
                 let prompt = &args[4];
                 prompt.split(' ').map(|s| s.to_string()).collect()
@@ -186,7 +749,7 @@ pub trait Cmd: ParserInfo {
                         }
                     };
 
-                    return Ok(())
+                    return Ok(());
                 }
                 Err(error) => {
                     return Err(error);
@@ -198,38 +761,54 @@ pub trait Cmd: ParserInfo {
     }
 
     fn complete_args(&mut self, tokens: &[String]) -> CliResult<Vec<CompOut>> {
+        self.complete_args_inherited(tokens, vec![])
+    }
+
+    /// completions for the last of `tokens`, with the global flags declared by parents in scope
+    fn complete_args_inherited(
+        &mut self,
+        tokens: &[String],
+        mut inherited: Vec<&mut dyn Input>,
+    ) -> CliResult<Vec<CompOut>> {
         let mut completions = vec![];
         if tokens.is_empty() {
             return Ok(completions);
         }
 
         let subcommands = self.subcommand_docs();
+        let infer_prefixes = self.docs().infer_prefixes;
 
         // recurse into subcommand?
-        if !subcommands.is_empty() && !tokens.is_empty() {
-            let token = &tokens[0];
-            let mut subcommand_index = None;
-            for (idx, subcommand) in subcommands.iter().enumerate() {
-                if &subcommand.name == token {
-                    subcommand_index = Some(idx);
-                }
-            }
+        if !subcommands.is_empty() {
+            let sub_pos = {
+                let mut symbols = self.symbols();
+                symbols.extend(inherited.iter_mut().map(|g| &mut **g as &mut dyn Input));
+                subcommand_position(&symbols, tokens, infer_prefixes)
+            };
 
-            // todo check this
-            if let Some(index) = subcommand_index {
-                return self.complete_subcommand(index, &tokens[1..]);
-            }
+            if let Some(sub_pos) = sub_pos {
+                let token = &tokens[sub_pos];
+                let subcommand_index = find_subcommand(&subcommands, token, infer_prefixes);
 
-            // print subcommands that begin with the token
-            if tokens.len() == 1 && !tokens[0].starts_with('-') {
-                for sub in subcommands {
-                    if sub.name.starts_with(token) {
-                        let name = &sub.name;
-                        let desc = &sub.description;
-                        completions.push(CompOut {
-                            name: name.to_string(),
-                            desc: desc.to_owned(),
-                        })
+                // todo check this
+                if let Ok(Some(index)) = subcommand_index {
+                    if sub_pos < tokens.len() - 1 {
+                        let tokens = forwarded_tokens(tokens, sub_pos);
+                        return self.complete_subcommand(index, &tokens, inherited);
+                    }
+                }
+
+                // print subcommands that begin with the token
+                if sub_pos == tokens.len() - 1 {
+                    for sub in subcommands {
+                        if !sub.hidden && sub.name.starts_with(token) {
+                            let name = &sub.name;
+                            let desc = &sub.description;
+                            completions.push(CompOut {
+                                name: name.to_string(),
+                                desc: desc.to_owned(),
+                            })
+                        }
                     }
                 }
 
@@ -238,35 +817,70 @@ pub trait Cmd: ParserInfo {
         }
 
         let has_version = self.docs().version.is_some();
+        let rules = self.docs().all_rules();
         let mut symbols = self.symbols();
+        symbols.extend(inherited.into_iter().map(|g| g as &mut dyn Input));
+
+        let trailing_at = symbols
+            .iter()
+            .filter(|sym| sym.type_name() == InputType::Arg)
+            .position(|sym| sym.is_trailing());
 
         let mut positional_args_so_far = 0;
-        if tokens.len() > 1 {
-            for token in &tokens[0..tokens.len() - 1] {
-                if !token.starts_with('-') {
-                    // in a future where we manage errors more properly, this section could be
-                    // closer to how the parser works, eliminating consumed symbols and helping
-                    // the end user not see completions for flags they've already typed. Presently
-                    // that code would start outputting errors.
-                    positional_args_so_far += 1;
+        let mut value_for = None;
+        let mut options_ended = false;
+        let mut used = vec![];
+        for token in &tokens[0..tokens.len() - 1] {
+            if value_for.take().is_some() {
+                continue;
+            }
+
+            if options_ended || !token.starts_with('-') || is_negative_number(token) {
+                options_ended |= trailing_at == Some(positional_args_so_far);
+                positional_args_so_far += 1;
+            } else if token == "--" {
+                options_ended = true;
+            } else {
+                let expanded = if infer_prefixes {
+                    expand_flag_prefix(&symbols, token).ok().flatten()
+                } else {
+                    None
+                };
+                let token = expanded.as_ref().unwrap_or(token);
+
+                // flags already typed aren't offered again, unless they can be repeated
+                let names = match short_bundle(token) {
+                    Some((shorts, _)) => shorts.iter().map(|c| format!("-{c}")).collect(),
+                    None => vec![token.to_string()],
+                };
+                for name in names {
+                    used.extend(symbols.iter().position(|sym| sym.is_named(&name)));
                 }
+                value_for = awaiting_value(&symbols, token);
             }
         }
 
         let token = &tokens[tokens.len() - 1];
-        if let Some(mut completion_token) = token.strip_prefix('-') {
+
+        // the previous token was a flag waiting for its value: `--editor <tab>`
+        if let Some(flag_idx) = value_for {
+            return value_completions(&mut *symbols[flag_idx], token);
+        }
+
+        if let Some(mut completion_token) = token
+            .strip_prefix('-')
+            .filter(|_| !options_ended && !is_negative_number(token))
+        {
             if let Some(second_dash_removed) = completion_token.strip_prefix('-') {
                 completion_token = second_dash_removed;
             }
-            let value_completion = completion_token.split('=').collect::<Vec<&str>>();
-            if value_completion.len() > 1 {
+            // only the first `=` ends the name, `--set=key=value`
+            if let Some((name, value)) = completion_token.split_once('=') {
                 for symbol in &mut symbols {
-                    if symbol.display_name() == value_completion[0] {
-                        for completion in symbol.complete(value_completion[1])? {
-                            completions.push(CompOut {
-                                name: format!("--{}={completion}", symbol.display_name()),
-                                desc: None,
-                            });
+                    if symbol.display_name() == name {
+                        let mut completions = value_completions(&mut **symbol, value)?;
+                        for completion in &mut completions {
+                            completion.name = format!("--{name}={}", completion.name);
                         }
                         return Ok(completions);
                     }
@@ -280,37 +894,69 @@ pub trait Cmd: ParserInfo {
                 built_in.push(&mut version);
             }
 
-            symbols
+            // flags that conflict with one already typed aren't offered either
+            let conflicting = conflicting(&rules, &symbols, &used);
+
+            let mut offered = symbols
                 .iter()
-                .chain(built_in.iter())
-                .filter(|sym| sym.type_name() == InputType::Flag)
-                .filter(|sym| sym.display_name().starts_with(completion_token))
-                .for_each(|flag| {
-                    if flag.is_bool_flag() {
+                .enumerate()
+                .filter(|(i, sym)| {
+                    sym.type_name() == InputType::Flag
+                        && !sym.is_hidden()
+                        && (!used.contains(i) || sym.is_repeatable())
+                        && !conflicting.contains(i)
+                })
+                .map(|(_, flag)| flag)
+                .collect::<Vec<_>>();
+            // flags that have to be given come first
+            offered.sort_by_key(|flag| flag.has_default());
+
+            // --help and --version have no negated form
+            let flags = offered
+                .into_iter()
+                .map(|flag| (flag, true))
+                .chain(built_in.iter().map(|flag| (flag, false)));
+
+            for (flag, negatable) in flags {
+                let name = flag.display_name();
+                if flag.is_bool_flag() {
+                    if name.starts_with(completion_token) {
                         completions.push(CompOut {
-                            name: format!("--{}", flag.display_name()),
+                            name: format!("--{name}"),
                             desc: flag.description(),
                         });
-                    } else {
+                    }
+
+                    let negated = format!("no-{name}");
+                    let negatable = negatable && flag.is_named(&format!("--{negated}"));
+                    if negatable && negated.starts_with(completion_token) {
                         completions.push(CompOut {
-                            name: format!("--{}=", flag.display_name()),
+                            name: format!("--{negated}"),
                             desc: flag.description(),
                         });
                     }
-                });
+                } else if name.starts_with(completion_token) {
+                    completions.push(CompOut {
+                        name: format!("--{name}="),
+                        desc: flag.description(),
+                    });
+                }
+            }
         } else {
-            let arg = symbols
+            let mut args = symbols
                 .iter_mut()
                 .filter(|sym| sym.type_name() == InputType::Arg)
-                .nth(positional_args_so_far);
+                .collect::<Vec<_>>();
+
+            // once every positional is filled, a trailing list keeps completing
+            let arg = if positional_args_so_far < args.len() {
+                Some(args.swap_remove(positional_args_so_far))
+            } else {
+                args.pop().filter(|arg| arg.is_repeatable())
+            };
 
             if let Some(arg) = arg {
-                for option in arg.complete(token)? {
-                    completions.push(CompOut {
-                        name: option.to_string(),
-                        desc: None,
-                    });
-                }
+                completions = value_completions(&mut **arg, token)?;
             }
         }
 
@@ -318,77 +964,242 @@ pub trait Cmd: ParserInfo {
     }
 
     fn parse_args(&mut self, tokens: &[String]) -> CliResult<()> {
+        let env = env::vars_os()
+            .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+            .collect();
+
+        self.parse_args_with_env(tokens, &env)
+    }
+
+    /// like `parse_args`, but env var fallbacks are read from `env` rather than the process
+    fn parse_args_with_env(
+        &mut self,
+        tokens: &[String],
+        env: &HashMap<String, String>,
+    ) -> CliResult<()> {
+        self.parse_args_inherited(tokens, env, vec![])
+    }
+
+    /// parses `tokens` with the global flags declared by parents in scope, any of them may appear
+    /// before or after the subcommand name
+    fn parse_args_inherited(
+        &mut self,
+        tokens: &[String],
+        env: &HashMap<String, String>,
+        mut inherited: Vec<&mut dyn Input>,
+    ) -> CliResult<()> {
         let subcommands = self.subcommand_docs();
+        let infer_prefixes = self.docs().infer_prefixes;
         let symbols = self.symbols();
-        let required_args = symbols.iter().filter(|f| !f.has_default()).count();
+        let required_args = symbols
+            .iter()
+            .filter(|f| !f.has_default() && !f.env_var().is_some_and(|var| env.contains_key(&var)))
+            .count();
+
+        let cmd_path = self.docs().cmd_path();
+        let missing = if subcommands.is_empty() {
+            ErrorKind::MissingArgument
+        } else {
+            ErrorKind::MissingSubcommand
+        };
 
-        if tokens.is_empty() && (required_args > 0 || !subcommands.is_empty()) {
-            return Err(self.gen_help());
+        if tokens.is_empty() && !subcommands.is_empty() {
+            let help = self.gen_help_inherited(inherited);
+            return Err(CliError::new(missing, help).in_cmd(&cmd_path));
         }
 
+        // run bare, the missing input is reported like any other but followed by help
+        let bare_help = (tokens.is_empty() && required_args > 0).then(|| {
+            let inherited = inherited.iter_mut().map(|g| &mut **g as &mut dyn Input);
+            self.gen_help_inherited(inherited.collect())
+        });
+
         // try to match subcommands
         if !tokens.is_empty() {
-            let token = &tokens[0];
-            if token == "--help" {
-                println!("{}", self.gen_help().msg);
-                return Ok(());
-            }
+            let (sub_pos, flags_end) = {
+                let mut symbols = self.symbols();
+                symbols.extend(inherited.iter_mut().map(|g| &mut **g as &mut dyn Input));
+                let sub_pos = if subcommands.is_empty() {
+                    None
+                } else {
+                    subcommand_position(&symbols, tokens, infer_prefixes)
+                };
+                (sub_pos, own_flags_end(&symbols, tokens, sub_pos))
+            };
 
-            if token == "--version" {
-                let docs = &self.docs();
-                if let Some(version) = &docs.version {
-                    println!("{} -- {}", docs.cmd_path(), version);
-                    return Ok(());
+            // `--help` anywhere among this command's own flags, `lb --verbose sync --help`
+            // forwards the global ahead of it
+            let has_version = self.docs().version.is_some();
+            let requested = tokens[..flags_end]
+                .iter()
+                .find(|token| *token == "--help" || (*token == "--version" && has_version));
+            if let Some(token) = requested {
+                if token == "--help" {
+                    let help = self.gen_help_inherited(inherited);
+                    return Err(CliError::new(ErrorKind::HelpRequested, help)
+                        .token(token)
+                        .in_cmd(&cmd_path));
                 }
+
+                let version = self.docs().version.as_ref().unwrap();
+                let version = format!("{cmd_path} -- {version}");
+                return Err(CliError::new(ErrorKind::VersionRequested, version)
+                    .token(token)
+                    .in_cmd(&cmd_path));
             }
+
             if !subcommands.is_empty() {
-                for (idx, subcommand) in subcommands.iter().enumerate() {
-                    if &subcommand.name == token {
-                        return self.parse_subcommand(idx, &tokens[1..]);
+                let Some(sub_pos) = sub_pos else {
+                    let help = self.gen_help_inherited(inherited);
+                    return Err(CliError::new(missing, help).in_cmd(&cmd_path));
+                };
+
+                let token = &tokens[sub_pos];
+                match find_subcommand(&subcommands, token, infer_prefixes) {
+                    Ok(Some(idx)) => {
+                        let tokens = forwarded_tokens(tokens, sub_pos);
+                        return self.parse_subcommand(idx, &tokens, env, inherited);
                     }
+                    Ok(None) => {}
+                    Err(candidates) => {
+                        let candidates = candidates.join(", ");
+                        let msg = format!("{token} is ambiguous, it could be {candidates}");
+                        return Err(CliError::new(ErrorKind::AmbiguousPrefix, msg)
+                            .token(token)
+                            .in_cmd(&cmd_path));
+                    }
+                }
+
+                let names = subcommands
+                    .into_iter()
+                    .filter(|sub| !sub.hidden)
+                    .map(|sub| sub.name)
+                    .collect::<Vec<_>>();
+                let mut msg = format!("{token} is not a valid subcommand");
+                if let Some(name) = closest(token, &names) {
+                    write!(msg, ", did you mean `{name}`?").unwrap();
                 }
+                write!(msg, "\n\nsubcommands: {}", names.join(", ")).unwrap();
 
-                return Err(CliError::from(format!("{token} is not a valid subcommand")));
+                return Err(CliError::new(ErrorKind::UnknownSubcommand, msg)
+                    .token(token)
+                    .in_cmd(&cmd_path));
             }
         }
 
+        let negative_numbers = self.docs().negative_numbers;
+        let rules = self.docs().all_rules();
         let mut symbols = self.symbols();
+        symbols.extend(inherited.into_iter().map(|g| g as &mut dyn Input));
+        check_rule_inputs(&cmd_path, &rules, &symbols);
 
-        for token in tokens {
-            if token.starts_with('-') {
-                let mut token_matched = false;
-                for symbol in &mut symbols {
-                    if !symbol.parsed() && symbol.type_name() == InputType::Flag {
-                        let consumed = symbol.parse(token)?;
-                        if consumed {
-                            token_matched = true;
-                        }
+        // set by `--`, or once a trailing input has its first value
+        let mut options_ended = false;
+        let mut idx = 0;
+        while idx < tokens.len() {
+            let token = &tokens[idx];
+            let positional = options_ended
+                || !token.starts_with('-')
+                || (negative_numbers && is_negative_number(token));
+
+            if positional {
+                match next_positional(&mut symbols) {
+                    Some(arg) => {
+                        // past `--` a token is a value even if it looks like a flag
+                        let parsed = if options_ended {
+                            arg.parse_value(token)
+                        } else {
+                            arg.parse(token).map(|_| ())
+                        };
+                        parsed.map_err(|err| err.in_cmd(&cmd_path))?;
+                        options_ended |= arg.is_trailing();
+                    }
+                    None => {
+                        return Err(CliError::new(
+                            ErrorKind::UnexpectedArgument,
+                            format!("Unexpected argument found {token}"),
+                        )
+                        .token(token)
+                        .in_cmd(&cmd_path));
                     }
                 }
-                if !token_matched {
-                    return Err(CliError::from(format!(
-                        "Unexpected flag-like token found {token}"
-                    )));
-                }
+                idx += 1;
+            } else if token == "--" {
+                options_ended = true;
+                idx += 1;
             } else {
-                'args: for symbol in &mut symbols {
-                    if !symbol.parsed() && symbol.type_name() == InputType::Arg {
-                        symbol.parse(token)?;
-                        break 'args;
+                // a `-5` no flag answers to is still a value if the next arg parses it, checked
+                // before a bundle like `-15` can set `-1` and then fail on `5`. The arg's
+                // validators still apply, `-5` for a `0..10` arg fails on its range
+                if is_negative_number(token) && !shorts_known(&symbols, token) {
+                    if let Some(arg) = next_positional(&mut symbols) {
+                        if arg.parses(token) {
+                            arg.parse_value(token)
+                                .map_err(|err| err.in_cmd(&cmd_path))?;
+                            idx += 1;
+                            continue;
+                        }
                     }
                 }
+
+                // `--verb` stands for `--verbose` from here on
+                let expanded = if infer_prefixes {
+                    expand_flag_prefix(&symbols, token).map_err(|err| err.in_cmd(&cmd_path))?
+                } else {
+                    None
+                };
+                let token = expanded.as_ref().unwrap_or(token);
+                // `--editor --force` leaves --editor without a value rather than taking `--force`
+                let next = tokens.get(idx + 1).filter(|next| {
+                    !next.starts_with('-')
+                        || *next == "-"
+                        || (is_negative_number(next)
+                            && (negative_numbers || !shorts_known(&symbols, next)))
+                });
+                let consumed = match short_bundle(token) {
+                    Some((shorts, value)) => {
+                        parse_bundle(&mut symbols, token, &shorts, value, next)
+                    }
+                    None => parse_flag(&mut symbols, token, next),
+                };
+
+                idx += consumed.map_err(|err| err.in_cmd(&cmd_path))?;
             }
         }
 
+        for symbol in &mut symbols {
+            if symbol.parsed() {
+                continue;
+            }
+
+            if let Some(value) = symbol.env_var().and_then(|var| env.get(&var)) {
+                symbol
+                    .parse_value(value)
+                    .map_err(|err| err.in_cmd(&cmd_path))?;
+            }
+        }
+
+        check_rules(&rules, &symbols, &cmd_path).map_err(|err| err.in_cmd(&cmd_path))?;
+
+        // anything left without a value or a default would panic when the handler reads it
         for symbol in symbols {
-            if symbol.type_name() == InputType::Arg && !symbol.has_default() && !symbol.parsed() {
-                return Err(CliError::from(format!(
-                    "Missing required argument: {}",
-                    symbol.display_name()
-                )));
+            if symbol.has_default() || symbol.parsed() {
+                continue;
+            }
+
+            let name = symbol.display_name();
+            let mut msg = match symbol.type_name() {
+                InputType::Arg => format!("Missing required argument: {name}"),
+                InputType::Flag => format!("Missing required flag --{name}"),
+            };
+            if let Some(help) = &bare_help {
+                write!(msg, "\n\n{help}").unwrap();
             }
+            return Err(CliError::new(ErrorKind::MissingArgument, msg)
+                .input(&name)
+                .in_cmd(&cmd_path));
         }
 
-        self.call_handler()
+        self.call_handler().map_err(|err| err.in_cmd(&cmd_path))
     }
 }
@@ -0,0 +1,320 @@
+/// a small regular expression matcher for `.pattern()` validators. Supports literals, `.`,
+/// classes like `[a-z_]` and `[^0-9]`, the escapes `\d`, `\w` and `\s`, the quantifiers `*`, `+`,
+/// `?`, `{n}`, `{n,}` and `{n,m}`, and the anchors `^` and `$`. Without anchors a pattern may
+/// match anywhere in the value. Anything else regular expressions give a meaning to, groups,
+/// alternation, other escapes like `\b` or anchors mid pattern, is rejected rather than matched
+/// literally.
+pub(crate) struct Pattern {
+    items: Vec<Item>,
+    anchored_start: bool,
+    anchored_end: bool,
+}
+
+struct Item {
+    atom: Atom,
+    min: usize,
+    max: Option<usize>,
+}
+
+enum Atom {
+    Any,
+    Char(char),
+    Class { members: Vec<Member>, negated: bool },
+}
+
+enum Member {
+    Range(char, char),
+    Digit,
+    Word,
+    Space,
+}
+
+impl Member {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Member::Range(low, high) => (*low..=*high).contains(&c),
+            Member::Digit => c.is_ascii_digit(),
+            Member::Word => c.is_alphanumeric() || c == '_',
+            Member::Space => c.is_whitespace(),
+        }
+    }
+
+    /// `\d`, `\w` and `\s`, `None` for an escaped literal like `\.`
+    fn escape(c: char) -> Result<Option<Self>, String> {
+        match c {
+            'd' => Ok(Some(Member::Digit)),
+            'w' => Ok(Some(Member::Word)),
+            's' => Ok(Some(Member::Space)),
+            c if c.is_alphanumeric() => Err(format!("\\{c} is not supported in patterns")),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Atom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Atom::Any => true,
+            Atom::Char(expected) => c == *expected,
+            Atom::Class { members, negated } => members.iter().any(|m| m.matches(c)) != *negated,
+        }
+    }
+}
+
+impl Pattern {
+    pub(crate) fn new(pattern: &str) -> Result<Self, String> {
+        let mut chars = pattern.chars().peekable();
+        let anchored_start = chars.next_if_eq(&'^').is_some();
+        let mut anchored_end = false;
+        let mut items = vec![];
+
+        while let Some(c) = chars.next() {
+            let atom = match c {
+                '$' if chars.peek().is_none() => {
+                    anchored_end = true;
+                    break;
+                }
+                '.' => Atom::Any,
+                '\\' => {
+                    let escaped = chars.next().ok_or("pattern ends with a lone \\")?;
+                    match Member::escape(escaped)? {
+                        Some(member) => Atom::Class {
+                            members: vec![member],
+                            negated: false,
+                        },
+                        None => Atom::Char(escaped),
+                    }
+                }
+                '[' => {
+                    let negated = chars.next_if_eq(&'^').is_some();
+                    let mut members = vec![];
+                    loop {
+                        let c = chars.next().ok_or("unclosed [ in pattern")?;
+                        let low = match c {
+                            ']' if !members.is_empty() => break,
+                            '[' => return Err("[ inside a class must be escaped as \\[".into()),
+                            '\\' => {
+                                let escaped = chars.next().ok_or("unclosed [ in pattern")?;
+                                if let Some(member) = Member::escape(escaped)? {
+                                    members.push(member);
+                                    continue;
+                                }
+                                escaped
+                            }
+                            c => c,
+                        };
+
+                        let high = match chars.next_if_eq(&'-') {
+                            Some(_) if chars.peek() == Some(&']') => {
+                                members.push(Member::Range('-', '-'));
+                                low
+                            }
+                            Some(_) => chars.next().ok_or("unclosed [ in pattern")?,
+                            None => low,
+                        };
+                        members.push(Member::Range(low, high));
+                    }
+                    Atom::Class { members, negated }
+                }
+                '*' | '+' | '?' | '{' => return Err(format!("nothing to repeat before {c}")),
+                '(' | ')' | '|' | '^' | '$' | ']' | '}' => {
+                    return Err(format!(
+                        "{c} is not supported here, escape it as \\{c} to match it"
+                    ))
+                }
+                c => Atom::Char(c),
+            };
+
+            let (min, max) = match chars.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{') => {
+                    chars.next();
+                    let mut bounds = String::new();
+                    loop {
+                        match chars.next().ok_or("unclosed { in pattern")? {
+                            '}' => break,
+                            c => bounds.push(c),
+                        }
+                    }
+                    let count = |n: &str| {
+                        n.trim()
+                            .parse::<usize>()
+                            .map_err(|_| format!("{{{bounds}}} is not a valid repetition"))
+                    };
+                    let (min, max) = match bounds.split_once(',') {
+                        None => (count(&bounds)?, Some(count(&bounds)?)),
+                        Some((min, "")) => (count(min)?, None),
+                        Some((min, max)) => (count(min)?, Some(count(max)?)),
+                    };
+                    if max.is_some_and(|max| max < min) {
+                        return Err(format!(
+                            "{{{bounds}}} allows fewer repeats than it requires"
+                        ));
+                    }
+                    items.push(Item { atom, min, max });
+                    continue;
+                }
+                _ => (1, Some(1)),
+            };
+            if (min, max) != (1, Some(1)) {
+                chars.next();
+            }
+
+            items.push(Item { atom, min, max });
+        }
+
+        Ok(Self {
+            items,
+            anchored_start,
+            anchored_end,
+        })
+    }
+
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        if self.anchored_start {
+            self.match_here(0, &text)
+        } else {
+            (0..=text.len()).any(|start| self.match_here(0, &text[start..]))
+        }
+    }
+
+    /// whether the items from `item` onwards match the start of `text`, backtracking from the
+    /// longest run of each item
+    fn match_here(&self, item: usize, text: &[char]) -> bool {
+        let Some(Item { atom, min, max }) = self.items.get(item) else {
+            return !self.anchored_end || text.is_empty();
+        };
+
+        let mut run = 0;
+        while run < text.len() && max.is_none_or(|max| run < max) && atom.matches(text[run]) {
+            run += 1;
+        }
+
+        while run >= *min {
+            if self.match_here(item + 1, &text[run..]) {
+                return true;
+            }
+            if run == 0 {
+                break;
+            }
+            run -= 1;
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pattern;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        Pattern::new(pattern).unwrap().is_match(text)
+    }
+
+    #[test]
+    fn literals_and_anchors() {
+        assert!(matches("^abc$", "abc"));
+        assert!(!matches("^abc$", "abcd"));
+        assert!(!matches("^abc$", "xabc"));
+        assert!(matches("^abc", "abcd"));
+        assert!(matches("abc$", "xabc"));
+    }
+
+    #[test]
+    fn unanchored_matches_anywhere() {
+        assert!(matches("b.d", "abcde"));
+        assert!(matches("", "anything"));
+        assert!(!matches("xyz", "abcde"));
+    }
+
+    #[test]
+    fn any_char() {
+        assert!(matches("^a.c$", "abc"));
+        assert!(matches("^a.c$", "a-c"));
+        assert!(matches("^a.c$", "aéc"));
+        assert!(!matches("^a.c$", "ac"));
+    }
+
+    #[test]
+    fn quantifiers() {
+        assert!(matches("^ab?c$", "ac"));
+        assert!(matches("^ab?c$", "abc"));
+        assert!(!matches("^ab?c$", "abbc"));
+
+        assert!(matches("^ab*c$", "ac"));
+        assert!(matches("^ab*c$", "abbbc"));
+        assert!(!matches("^ab+c$", "ac"));
+        assert!(matches("^ab+c$", "abbc"));
+    }
+
+    #[test]
+    fn counted_repetition() {
+        assert!(matches("^a{3}$", "aaa"));
+        assert!(!matches("^a{3}$", "aa"));
+        assert!(!matches("^a{3}$", "aaaa"));
+
+        assert!(matches("^a{2,}$", "aa"));
+        assert!(matches("^a{2,}$", "aaaaa"));
+        assert!(!matches("^a{2,}$", "a"));
+
+        assert!(matches("^a{1,3}$", "a"));
+        assert!(matches("^a{1,3}$", "aaa"));
+        assert!(!matches("^a{1,3}$", "aaaa"));
+    }
+
+    #[test]
+    fn backtracks_into_earlier_repeats() {
+        assert!(matches("^a*ab$", "aaab"));
+        assert!(matches("^.*\\.md$", "notes.v2.md"));
+        assert!(!matches("^.*\\.md$", "notes.mdx"));
+    }
+
+    #[test]
+    fn classes() {
+        assert!(matches("^[a-c_]+$", "ab_c"));
+        assert!(!matches("^[a-c_]+$", "abd"));
+        assert!(matches("^[^0-9]+$", "abc"));
+        assert!(!matches("^[^0-9]+$", "ab1"));
+        assert!(matches("^[a-]+$", "a-a"));
+        assert!(matches("^[]a]+$", "]a"));
+        assert!(matches("^[\\d.]+$", "1.25"));
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(matches("^\\d+$", "2024"));
+        assert!(!matches("^\\d+$", "20x4"));
+        assert!(matches("^\\w+$", "snake_case1"));
+        assert!(!matches("^\\w+$", "kebab-case"));
+        assert!(matches("^a\\sb$", "a b"));
+        assert!(matches("^\\$\\.\\*$", "$.*"));
+    }
+
+    #[test]
+    fn rejects_unsupported_syntax() {
+        for pattern in [
+            "^(foo|bar)$",
+            "foo|bar",
+            "a)",
+            "a^b",
+            "a$b",
+            "a]",
+            "a}",
+            "[[:alpha:]]",
+            "\\bword",
+            "\\D",
+            "*a",
+            "a{x}",
+            "a{3,1}",
+            "[abc",
+            "a{2",
+            "a\\",
+        ] {
+            assert!(Pattern::new(pattern).is_err(), "{pattern} was accepted");
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{
+    cli_error::CliResult,
+    input::{Completor, Input, InputType},
+};
+
+/// every token from the first positional onwards, kept verbatim even when it looks like a flag,
+/// for commands that hand their arguments to something else: `lockbook exec -- git status --short`.
+/// Must be the last positional input of a command, `.input()` panics otherwise.
+pub struct TrailingArgs<'a> {
+    pub name: String,
+    pub description: Option<String>,
+    pub values: Vec<String>,
+    pub completor: Option<Completor<'a>>,
+}
+
+impl<'a> TrailingArgs<'a> {
+    pub fn name(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: None,
+            values: vec![],
+            completor: None,
+        }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    pub fn get(&self) -> Vec<String> {
+        self.values.clone()
+    }
+
+    pub fn completor<F>(mut self, completor: F) -> Self
+    where
+        F: FnMut(&str) -> CliResult<Vec<String>> + 'a,
+    {
+        self.completor = Some(Box::new(completor));
+        self
+    }
+}
+
+impl<'a> Input for TrailingArgs<'a> {
+    fn parse(&mut self, token: &str) -> CliResult<bool> {
+        self.parse_value(token)?;
+
+        Ok(true)
+    }
+
+    fn expects_value(&self, _token: &str) -> bool {
+        false
+    }
+
+    fn parse_value(&mut self, value: &str) -> CliResult<()> {
+        self.values.push(value.to_string());
+
+        Ok(())
+    }
+
+    fn display_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn short_name(&self) -> Option<char> {
+        None
+    }
+
+    fn type_name(&self) -> InputType {
+        InputType::Arg
+    }
+
+    fn parsed(&self) -> bool {
+        !self.values.is_empty()
+    }
+
+    fn is_repeatable(&self) -> bool {
+        true
+    }
+
+    fn is_trailing(&self) -> bool {
+        true
+    }
+
+    fn complete(&mut self, value: &str) -> CliResult<Vec<String>> {
+        if let Some(completor) = &mut self.completor {
+            completor(value)
+        } else {
+            Ok(vec![])
+        }
+    }
+
+    fn is_bool_flag(&self) -> bool {
+        false
+    }
+
+    fn description(&self) -> Option<String> {
+        self.description.clone()
+    }
+
+    fn has_default(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,100 @@
+use crate::cli_error::{CliError, CliResult};
+
+pub use cli_rs_command_gen::ValueEnum;
+
+/// a type whose values are a fixed set of names, usually through `#[derive(ValueEnum)]`. An
+/// `Arg::value_enum` or `Flag::value_enum` over it parses, completes and lists its values in help
+/// without a completor. `#[value(from_str, display)]` also implements `FromStr` and `Display`
+/// with the names, for `.show_default()` or parsing them elsewhere.
+///
+/// ```ignore
+/// #[derive(ValueEnum, Clone)]
+/// #[value(display)]
+/// enum Shell {
+///     Bash,
+///     /// the friendly interactive shell
+///     Fish,
+///     #[value(alias = "z")]
+///     Zsh,
+/// }
+/// ```
+pub trait ValueEnum: Sized + Clone {
+    /// every value, in the order help and completions list them
+    fn variants() -> Vec<Self>;
+
+    fn possible_value(&self) -> PossibleValue;
+
+    fn possible_values() -> Vec<PossibleValue> {
+        Self::variants().iter().map(Self::possible_value).collect()
+    }
+
+    /// the value named `name` or one of its aliases, ignoring case
+    fn from_name(name: &str) -> Option<Self> {
+        Self::variants()
+            .into_iter()
+            .find(|variant| variant.possible_value().matches(name))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PossibleValue {
+    pub name: String,
+    /// other names parsing accepts, completions and help only show `name`
+    pub aliases: Vec<String>,
+    pub description: Option<String>,
+}
+
+impl PossibleValue {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            aliases: vec![],
+            description: None,
+        }
+    }
+
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.aliases.push(alias.to_string());
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+            || self
+                .aliases
+                .iter()
+                .any(|alias| alias.eq_ignore_ascii_case(name))
+    }
+}
+
+/// the names of `values` as help and errors list them, `bash, zsh, fish`
+pub(crate) fn list(values: &[PossibleValue]) -> String {
+    values
+        .iter()
+        .map(|value| value.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `T`'s value for the token `value`, or an error naming `input` and listing the choices
+pub(crate) fn parse<T: ValueEnum>(value: &str, input: &str) -> CliResult<T> {
+    T::from_name(value).ok_or_else(|| {
+        let expected = format!("expected one of {}", list(&T::possible_values()));
+        CliError::unparsable_because(value, input, expected)
+    })
+}
+
+/// the names in `values` that start with `prompt`, ignoring case like parsing does
+pub(crate) fn complete(values: &[PossibleValue], prompt: &str) -> Vec<String> {
+    let prompt = prompt.to_ascii_lowercase();
+    values
+        .iter()
+        .filter(|value| value.name.to_ascii_lowercase().starts_with(&prompt))
+        .map(|value| value.name.clone())
+        .collect()
+}
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+
+use cli_rs::{
+    command::{Command, CommandDef},
+    parser::Cmd,
+    value_enum::ValueEnum,
+};
+
+/// edit a document
+#[derive(Command, Debug, PartialEq)]
+struct Edit {
+    /// the editor to open the document with
+    #[flag(short = 'E', env = "LOCKBOOK_EDITOR")]
+    editor: String,
+
+    #[flag]
+    force: bool,
+
+    /// the document to edit
+    target: String,
+}
+
+/// a secure, private notebook
+#[derive(Command, Debug, PartialEq)]
+#[command(version = "0.9.0", completions)]
+enum Lockbook {
+    Edit(Edit),
+
+    /// delete documents
+    Rm {
+        #[flag]
+        recursive: bool,
+        #[flag(count)]
+        verbose: usize,
+        #[flag]
+        exclude: Vec<String>,
+        files: Vec<String>,
+    },
+
+    /// sync with the server
+    Sync,
+
+    /// show the tree under a folder
+    Tree {
+        #[flag]
+        color: Option<bool>,
+        folder: Option<String>,
+    },
+}
+
+/// parses with `env` in place of the process environment, so a `LOCKBOOK_EDITOR` set where the
+/// tests run can't leak into `Edit`
+fn parse_with_env(tokens: &[&str], env: &HashMap<String, String>) -> Option<Lockbook> {
+    let mut parsed = None;
+    Lockbook::handler(|lockbook| {
+        parsed = Some(lockbook);
+        Ok(())
+    })
+    .parse_args_with_env(
+        &tokens.iter().map(|t| t.to_string()).collect::<Vec<_>>(),
+        env,
+    )
+    .unwrap();
+
+    parsed
+}
+
+fn parse(tokens: &[&str]) -> Option<Lockbook> {
+    parse_with_env(tokens, &HashMap::new())
+}
+
+#[test]
+fn derived_env() {
+    let env = HashMap::from([("LOCKBOOK_EDITOR".to_string(), "vim".to_string())]);
+    assert_eq!(
+        parse_with_env(&["edit", "todo.md"], &env),
+        Some(Lockbook::Edit(Edit {
+            editor: "vim".to_string(),
+            force: false,
+            target: "todo.md".to_string(),
+        }))
+    );
+}
+
+#[test]
+fn derived_struct() {
+    let mut edit = None;
+
+    Edit::handler(|e| {
+        edit = Some(e);
+        Ok(())
+    })
+    .parse_args(&["-E".to_string(), "nvim".to_string(), "todo.md".to_string()])
+    .unwrap();
+
+    assert_eq!(
+        edit,
+        Some(Edit {
+            editor: "nvim".to_string(),
+            force: false,
+            target: "todo.md".to_string(),
+        })
+    );
+}
+
+#[test]
+fn derived_enum() {
+    assert_eq!(
+        parse(&["edit", "--force", "todo.md"]),
+        Some(Lockbook::Edit(Edit {
+            editor: String::default(),
+            force: true,
+            target: "todo.md".to_string(),
+        }))
+    );
+    assert_eq!(
+        parse(&["rm", "-rvv", "a.md", "-e", "b.md", "c.md"]),
+        Some(Lockbook::Rm {
+            recursive: true,
+            verbose: 2,
+            exclude: vec!["b.md".to_string()],
+            files: vec!["a.md".to_string(), "c.md".to_string()],
+        })
+    );
+    assert_eq!(parse(&["sync"]), Some(Lockbook::Sync));
+    assert_eq!(
+        parse(&["tree"]),
+        Some(Lockbook::Tree {
+            color: None,
+            folder: None
+        })
+    );
+    assert_eq!(
+        parse(&["tree", "--no-color", "docs"]),
+        Some(Lockbook::Tree {
+            color: Some(false),
+            folder: Some("docs".to_string())
+        })
+    );
+}
+
+#[test]
+fn derived_docs() {
+    assert_eq!(Lockbook::NAME, "lockbook");
+    assert_eq!(Lockbook::DESCRIPTION, Some("a secure, private notebook"));
+
+    let help = Lockbook::handler(|_| Ok(())).gen_help();
+    assert!(help.contains("edit a document"));
+    assert!(help.contains("delete documents"));
+    assert!(help.contains("completions"));
+}
+
+#[test]
+fn derived_completions() {
+    let completions = Lockbook::handler(|_| Ok(()))
+        .complete_args(&["edit".to_string(), "--ed".to_string()])
+        .unwrap();
+
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].name, "--editor=");
+    assert_eq!(
+        completions[0].desc.as_deref(),
+        Some("the editor to open the document with")
+    );
+}
+
+#[derive(Command, Debug, PartialEq)]
+struct Export {
+    #[flag]
+    a: bool,
+    #[flag]
+    b: bool,
+    #[flag]
+    c: bool,
+    #[flag]
+    d: bool,
+    #[flag]
+    e: bool,
+    #[flag]
+    f: bool,
+    #[flag]
+    g: bool,
+    target: String,
+}
+
+#[test]
+fn derived_many_fields() {
+    let mut export = None;
+
+    Export::handler(|e| {
+        export = Some(e);
+        Ok(())
+    })
+    .parse_args(&["-bg".to_string(), "a.md".to_string()])
+    .unwrap();
+
+    assert_eq!(
+        export,
+        Some(Export {
+            a: false,
+            b: true,
+            c: false,
+            d: false,
+            e: false,
+            f: false,
+            g: true,
+            target: "a.md".to_string(),
+        })
+    );
+}
+
+#[derive(ValueEnum, Clone, Debug, PartialEq)]
+#[value(from_str, display)]
+enum Format {
+    Markdown,
+    /// a pdf rendered with the default theme
+    #[value(alias = "portable")]
+    Pdf,
+    #[value(name = "txt")]
+    PlainText,
+}
+
+#[derive(Command, Debug, PartialEq)]
+struct Render {
+    #[arg(value_enum)]
+    format: Format,
+    #[flag(value_enum)]
+    fallback: Option<Format>,
+}
+
+#[test]
+fn derived_value_enum() {
+    assert_eq!(Format::from_name("PORTABLE"), Some(Format::Pdf));
+    assert_eq!("txt".parse(), Ok(Format::PlainText));
+    assert_eq!(Format::Markdown.to_string(), "markdown");
+    assert_eq!(
+        "html".parse::<Format>(),
+        Err("expected one of markdown, pdf, txt".to_string())
+    );
+
+    let mut render = None;
+    Render::handler(|r| {
+        render = Some(r);
+        Ok(())
+    })
+    .parse_args(&["portable".to_string(), "--fallback=txt".to_string()])
+    .unwrap();
+
+    assert_eq!(
+        render,
+        Some(Render {
+            format: Format::Pdf,
+            fallback: Some(Format::PlainText),
+        })
+    );
+
+    let completions = Render::handler(|_| Ok(()))
+        .complete_args(&["--fallback=p".to_string()])
+        .unwrap();
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].name, "--fallback=pdf");
+}
+
+/// lockbook with shortened names
+#[derive(Command, Debug, PartialEq)]
+#[command(infer_prefixes)]
+enum Short {
+    Sync {
+        #[flag]
+        verbose: bool,
+    },
+    Share,
+}
+
+#[test]
+fn derived_infer_prefixes() {
+    let mut short = None;
+    Short::handler(|s| {
+        short = Some(s);
+        Ok(())
+    })
+    .parse_args(&["sy".to_string(), "--verb".to_string()])
+    .unwrap();
+
+    assert_eq!(short, Some(Short::Sync { verbose: true }));
+}
@@ -1,6 +1,20 @@
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
-use cli_rs::{arg::Arg, command::Command, flag::Flag, parser::Cmd};
+use cli_rs::{
+    arg::Arg,
+    cli_error::{CliResult, ErrorKind},
+    command::Command,
+    count_flag::CountFlag,
+    flag::Flag,
+    global::Global,
+    list_arg::ListArg,
+    list_flag::ListFlag,
+    map_flag::MapFlag,
+    opt_arg::OptArg,
+    parser::Cmd,
+    trailing_args::TrailingArgs,
+    value_enum::ValueEnum,
+};
 
 #[test]
 fn basic_command() {
@@ -85,6 +99,10 @@ fn arg_parsing_failure() {
         .unwrap_err();
 }
 
+fn tokens(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
 #[derive(Default, Debug, PartialEq, Clone, Copy)]
 pub enum FileType {
     #[default]
@@ -198,10 +216,87 @@ fn short_flag_upper() {
             Ok(())
         })
         .parse_args(&["todo.md".to_string(), "-C".to_string()])
+        .unwrap_err();
+
+    assert_eq!(name, String::default());
+    assert!(!create);
+}
+
+#[test]
+fn explicit_short_flag() {
+    let mut create = false;
+    let mut config = String::default();
+
+    Command::name("edit")
+        .input(Flag::bool("create"))
+        .input(Flag::<String>::new("config").short('C'))
+        .handler(|c, cfg| {
+            create = c.get();
+            config = cfg.get();
+            Ok(())
+        })
+        .parse_args(&["-c".to_string(), "-C".to_string(), "lb.toml".to_string()])
         .unwrap();
 
-    assert_eq!(name, "todo.md");
     assert!(create);
+    assert_eq!(config, "lb.toml");
+}
+
+#[test]
+fn no_short_flag() {
+    Command::name("edit")
+        .input(Flag::bool("create").no_short())
+        .handler(|_| unreachable!())
+        .parse_args(&["-c".to_string()])
+        .unwrap_err();
+}
+
+#[test]
+#[should_panic(expected = "-c")]
+fn duplicate_short_flags() {
+    let _ = Command::name("edit")
+        .input(Flag::bool("create"))
+        .input(Flag::<String>::new("config"));
+}
+
+#[test]
+#[should_panic(expected = "lb sync: --validate and the inherited global --verbose both use -v")]
+fn subcommand_short_clashes_with_global() {
+    let _ = Command::name("lb")
+        .global(&Global::new(Flag::bool("verbose")))
+        .subcommand(
+            Command::name("sync")
+                .input(Flag::bool("validate"))
+                .handler(|_| Ok(())),
+        );
+}
+
+#[test]
+#[should_panic(
+    expected = "lb sync: --verbose and the inherited global --verbose both use --verbose"
+)]
+fn global_long_clashes_with_subcommand() {
+    let _ = Command::name("lb")
+        .subcommand(
+            Command::name("sync")
+                .input(Flag::bool("verbose").no_short())
+                .handler(|_| Ok(())),
+        )
+        .global(&Global::new(Flag::bool("verbose")));
+}
+
+#[test]
+#[should_panic(expected = "info: --validate and the inherited global --verbose both use -v")]
+fn nested_subcommand_clashes_with_global() {
+    let _ = Command::name("lb")
+        .global(&Global::new(Flag::bool("verbose")))
+        .subcommand(
+            Command::name("debug").subcommand(
+                Command::name("info")
+                    .input(Flag::bool("validate"))
+                    .handler(|_| Ok(())),
+            ),
+        );
 }
 
 #[test]
@@ -300,3 +395,1813 @@ fn subcommands_mismatch() {
 
     assert_eq!(path, String::default());
 }
+
+#[test]
+fn flag_value_after_space() {
+    let mut name = String::default();
+    let mut editor = String::default();
+
+    Command::name("edit")
+        .input(Flag::<String>::new("editor"))
+        .input(Arg::str("name"))
+        .handler(|e, n| {
+            editor = e.get();
+            name = n.get();
+            Ok(())
+        })
+        .parse_args(&[
+            "--editor".to_string(),
+            "nvim".to_string(),
+            "file.md".to_string(),
+        ])
+        .unwrap();
+
+    assert_eq!(editor, "nvim");
+    assert_eq!(name, "file.md");
+}
+
+#[test]
+fn short_flag_value_after_space() {
+    let mut editor = String::default();
+
+    Command::name("edit")
+        .input(Flag::<String>::new("editor"))
+        .handler(|e| {
+            editor = e.get();
+            Ok(())
+        })
+        .parse_args(&["-e".to_string(), "vim".to_string()])
+        .unwrap();
+
+    assert_eq!(editor, "vim");
+}
+
+#[test]
+fn flag_missing_value() {
+    Command::name("edit")
+        .input(Flag::<String>::new("editor"))
+        .handler(|_| unreachable!())
+        .parse_args(&["--editor".to_string()])
+        .unwrap_err();
+}
+
+#[test]
+fn flag_value_looks_like_flag() {
+    let err = Command::name("edit")
+        .input(Flag::<String>::new("editor"))
+        .input(Flag::bool("force"))
+        .handler(|_, _| unreachable!())
+        .parse_args(&tokens(&["--editor", "--force"]))
+        .unwrap_err();
+
+    assert_eq!(err.kind, ErrorKind::MissingValue);
+    assert_eq!(err.input.as_deref(), Some("editor"));
+
+    let mut seen = None;
+    Command::name("seek")
+        .input(Flag::<i32>::new("offset"))
+        .input(Flag::<String>::new("out").no_short())
+        .handler(|offset, out| {
+            seen = Some((offset.get(), out.get()));
+            Ok(())
+        })
+        .parse_args(&tokens(&["--offset", "-5", "--out", "-"]))
+        .unwrap();
+
+    assert_eq!(seen, Some((-5, "-".to_string())));
+}
+
+#[test]
+fn complete_flag_value_after_space() {
+    let completions = Command::name("edit")
+        .input(Flag::<String>::new("editor").completor(|prompt| {
+            Ok(["vim", "nvim", "nano"]
+                .iter()
+                .filter(|editor| editor.starts_with(prompt))
+                .map(|s| s.to_string())
+                .collect())
+        }))
+        .input(Arg::str("name"))
+        .handler(|_, _| Ok(()))
+        .complete_args(&["--editor".to_string(), "n".to_string()])
+        .unwrap()
+        .into_iter()
+        .map(|c| c.name)
+        .collect::<Vec<String>>();
+
+    assert_eq!(completions, vec!["nvim", "nano"]);
+}
+
+#[test]
+fn short_flag_bundle() {
+    let mut recursive = false;
+    let mut force = false;
+    let mut verbose = false;
+
+    Command::name("rm")
+        .input(Flag::bool("recursive"))
+        .input(Flag::bool("force"))
+        .input(Flag::bool("verbose"))
+        .handler(|r, f, v| {
+            recursive = r.get();
+            force = f.get();
+            verbose = v.get();
+            Ok(())
+        })
+        .parse_args(&["-rfv".to_string()])
+        .unwrap();
+
+    assert!(recursive);
+    assert!(force);
+    assert!(verbose);
+}
+
+#[test]
+fn short_flag_bundle_with_value() {
+    for tokens in [
+        vec!["-fo=out.txt".to_string()],
+        vec!["-fo".to_string(), "out.txt".to_string()],
+    ] {
+        let mut force = false;
+        let mut out = String::default();
+
+        Command::name("export")
+            .input(Flag::bool("force"))
+            .input(Flag::<String>::new("output"))
+            .handler(|f, o| {
+                force = f.get();
+                out = o.get();
+                Ok(())
+            })
+            .parse_args(&tokens)
+            .unwrap();
+
+        assert!(force);
+        assert_eq!(out, "out.txt");
+    }
+}
+
+#[test]
+fn short_flag_bundle_unknown() {
+    let err = Command::name("rm")
+        .input(Flag::bool("recursive"))
+        .input(Flag::bool("force"))
+        .handler(|_, _| unreachable!())
+        .parse_args(&["-rxf".to_string()])
+        .unwrap_err();
+
+    assert!(err.msg.contains("-x"));
+}
+
+#[test]
+fn list_arg() {
+    let mut files = vec![];
+
+    Command::name("rm")
+        .input(ListArg::<String>::name("files"))
+        .handler(|f| {
+            files = f.get();
+            Ok(())
+        })
+        .parse_args(&["a.md".to_string(), "b.md".to_string(), "c.md".to_string()])
+        .unwrap();
+
+    assert_eq!(files, vec!["a.md", "b.md", "c.md"]);
+}
+
+#[test]
+fn list_arg_empty() {
+    let mut files = vec!["untouched".to_string()];
+
+    Command::name("rm")
+        .input(ListArg::<String>::name("files"))
+        .handler(|f| {
+            files = f.get();
+            Ok(())
+        })
+        .parse_args(&[])
+        .unwrap();
+
+    assert!(files.is_empty());
+}
+
+#[test]
+fn opt_arg() {
+    let mut source = String::default();
+    let mut target = Some("untouched".to_string());
+
+    Command::name("sync")
+        .input(Arg::str("source"))
+        .input(OptArg::<String>::name("target"))
+        .handler(|s, t| {
+            source = s.get();
+            target = t.get();
+            Ok(())
+        })
+        .parse_args(&["a.md".to_string()])
+        .unwrap();
+
+    assert_eq!(source, "a.md");
+    assert_eq!(target, None);
+}
+
+#[test]
+fn non_ascii_arg() {
+    let mut target = String::new();
+
+    Command::name("rm")
+        .input(Arg::str("target"))
+        .handler(|t| {
+            target = t.get();
+            Ok(())
+        })
+        .parse_args(&["éa".to_string()])
+        .unwrap();
+
+    assert_eq!(target, "éa");
+}
+
+#[test]
+fn non_ascii_opt_arg() {
+    let mut target = None;
+
+    Command::name("rm")
+        .input(OptArg::<String>::name("target"))
+        .handler(|t| {
+            target = t.get();
+            Ok(())
+        })
+        .parse_args(&["aé".to_string()])
+        .unwrap();
+
+    assert_eq!(target.as_deref(), Some("aé"));
+}
+
+#[test]
+fn non_ascii_list_arg() {
+    let mut files = vec![];
+
+    Command::name("rm")
+        .input(ListArg::<String>::name("files"))
+        .handler(|f| {
+            files = f.get();
+            Ok(())
+        })
+        .parse_args(&["aé".to_string(), "café.md".to_string()])
+        .unwrap();
+
+    assert_eq!(files, vec!["aé", "café.md"]);
+}
+
+#[test]
+#[should_panic(expected = "cp: <files> takes every remaining value")]
+fn arg_after_list_arg() {
+    let _ = Command::name("cp")
+        .input(ListArg::<String>::name("files"))
+        .input(Arg::<String>::name("dest"));
+}
+
+#[test]
+#[should_panic(expected = "sync: [target] is optional")]
+fn required_arg_after_opt_arg() {
+    let _ = Command::name("sync")
+        .input(OptArg::<String>::name("target"))
+        .input(Arg::<String>::name("folder"));
+}
+
+#[test]
+fn extra_args() {
+    Command::name("edit")
+        .input(Arg::str("target"))
+        .handler(|_| unreachable!())
+        .parse_args(&["a.md".to_string(), "b.md".to_string()])
+        .unwrap_err();
+}
+
+#[test]
+fn help_shows_arg_usage() {
+    let help = Command::name("mv")
+        .input(OptArg::<String>::name("target"))
+        .input(ListArg::<String>::name("files"))
+        .handler(|_, _| Ok(()))
+        .gen_help();
+
+    assert!(help.contains("[target]"));
+    assert!(help.contains("<files>..."));
+}
+
+#[test]
+fn complete_list_arg() {
+    let completions = Command::name("rm")
+        .input(ListArg::<String>::name("files").completor(|prompt| {
+            Ok(["a.md", "b.md"]
+                .iter()
+                .filter(|file| file.starts_with(prompt))
+                .map(|s| s.to_string())
+                .collect())
+        }))
+        .handler(|_| Ok(()))
+        .complete_args(&["a.md".to_string(), "b.md".to_string(), "b".to_string()])
+        .unwrap();
+
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].name, "b.md");
+}
+
+#[test]
+fn env_fallback() {
+    let mut editor = String::default();
+    let mut target = String::default();
+    let env = HashMap::from([
+        ("LOCKBOOK_EDITOR".to_string(), "nvim".to_string()),
+        ("LOCKBOOK_TARGET".to_string(), "todo.md".to_string()),
+    ]);
+
+    Command::name("edit")
+        .input(Flag::<String>::new("editor").env("LOCKBOOK_EDITOR"))
+        .input(Arg::str("target").env("LOCKBOOK_TARGET"))
+        .handler(|e, t| {
+            editor = e.get();
+            target = t.get();
+            Ok(())
+        })
+        .parse_args_with_env(&[], &env)
+        .unwrap();
+
+    assert_eq!(editor, "nvim");
+    assert_eq!(target, "todo.md");
+}
+
+#[test]
+fn env_fallback_loses_to_cli() {
+    let mut editor = String::default();
+    let env = HashMap::from([("LOCKBOOK_EDITOR".to_string(), "nvim".to_string())]);
+
+    Command::name("lockbook")
+        .subcommand(
+            Command::name("edit")
+                .input(Flag::<String>::new("editor").env("LOCKBOOK_EDITOR"))
+                .handler(|e| {
+                    editor = e.get();
+                    Ok(())
+                }),
+        )
+        .parse_args_with_env(&["edit".to_string(), "--editor=vim".to_string()], &env)
+        .unwrap();
+
+    assert_eq!(editor, "vim");
+}
+
+#[test]
+fn help_shows_env_var() {
+    let help = Command::name("edit")
+        .input(Flag::<String>::new("editor").env("LOCKBOOK_EDITOR"))
+        .handler(|_| Ok(()))
+        .gen_help();
+
+    assert!(help.contains("[env: LOCKBOOK_EDITOR]"));
+}
+
+#[test]
+fn global_flags() {
+    for args in [
+        ["--verbose", "--config=lb.toml", "edit", "a.md"],
+        ["edit", "a.md", "--verbose", "--config=lb.toml"],
+        ["--config=lb.toml", "edit", "--verbose", "a.md"],
+    ] {
+        let verbose = Global::new(Flag::bool("verbose"));
+        let config = Global::new(Flag::<String>::new("config"));
+        let mut seen = None;
+
+        Command::name("lockbook")
+            .global(&verbose)
+            .global(&config)
+            .subcommand(
+                Command::name("edit")
+                    .input(Arg::str("target"))
+                    .handler(|t| {
+                        seen = Some((t.get(), verbose.borrow().get(), config.borrow().get()));
+                        Ok(())
+                    }),
+            )
+            .parse_args(&tokens(&args))
+            .unwrap();
+
+        assert_eq!(
+            seen,
+            Some(("a.md".to_string(), true, "lb.toml".to_string()))
+        );
+    }
+}
+
+#[test]
+fn help_and_version_after_global_flag() {
+    let lockbook = || {
+        Command::name("lb")
+            .version("0.9.0")
+            .global(&Global::new(Flag::bool("verbose")))
+            .subcommand(Command::name("sync").handler(|| Ok(())))
+    };
+
+    let err = lockbook()
+        .parse_args(&tokens(&["--verbose", "sync", "--help"]))
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::HelpRequested);
+    assert!(err.msg.contains("sync"));
+    assert_eq!(err.cmd_path.as_deref(), Some("lb sync"));
+
+    let err = lockbook()
+        .parse_args(&tokens(&["--verbose", "--version"]))
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::VersionRequested);
+    assert_eq!(err.msg, "lb -- 0.9.0");
+
+    // past the start of trailing args --help belongs to them
+    let mut forwarded = vec![];
+    Command::name("lb")
+        .global(&Global::new(Flag::bool("verbose")))
+        .subcommand(
+            Command::name("exec")
+                .input(TrailingArgs::name("command"))
+                .handler(|command| {
+                    forwarded = command.get();
+                    Ok(())
+                }),
+        )
+        .parse_args(&tokens(&["--verbose", "exec", "git", "--help"]))
+        .unwrap();
+    assert_eq!(forwarded, ["git", "--help"]);
+}
+
+#[test]
+fn global_flag_value_before_subcommand() {
+    let config = Global::new(Flag::<String>::new("config"));
+    let mut called = false;
+
+    Command::name("lockbook")
+        .global(&config)
+        .subcommand(Command::name("sync").handler(|| {
+            called = true;
+            Ok(())
+        }))
+        .parse_args(&[
+            "--config".to_string(),
+            "lb.toml".to_string(),
+            "sync".to_string(),
+        ])
+        .unwrap();
+
+    assert!(called);
+    assert_eq!(config.borrow().get(), "lb.toml");
+}
+
+#[test]
+fn complete_global_flags() {
+    let verbose = Global::new(Flag::bool("verbose"));
+
+    let completions = Command::name("lockbook")
+        .global(&verbose)
+        .subcommand(Command::name("sync").handler(|| Ok(())))
+        .complete_args(&["sync".to_string(), "--ver".to_string()])
+        .unwrap();
+
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].name, "--verbose");
+}
+
+#[test]
+fn more_than_six_inputs() {
+    let mut flags = vec![];
+
+    Command::name("share")
+        .input(Flag::bool("a"))
+        .input(Flag::bool("b"))
+        .input(Flag::bool("c"))
+        .input(Flag::bool("d"))
+        .input(Flag::bool("e"))
+        .input(Flag::bool("f"))
+        .input(Flag::bool("g"))
+        .input(Arg::str("target"))
+        .handler(|a, b, c, d, e, f, g, target| {
+            flags = vec![
+                a.get(),
+                b.get(),
+                c.get(),
+                d.get(),
+                e.get(),
+                f.get(),
+                g.get(),
+            ];
+            assert_eq!(target.get(), "a.md");
+            Ok(())
+        })
+        .parse_args(&["-ag".to_string(), "a.md".to_string()])
+        .unwrap();
+
+    assert_eq!(flags, vec![true, false, false, false, false, false, true]);
+}
+
+#[test]
+fn handler_list() {
+    let mut seen = (String::default(), 0, false);
+
+    Command::name("nameage")
+        .input(Arg::str("name"))
+        .input(Arg::i32("age"))
+        .input(Flag::bool("verbose"))
+        .handler_list(|(name, (age, (verbose, ())))| {
+            seen = (name.get(), age.get(), verbose.get());
+            Ok(())
+        })
+        .parse_args(&["parth".to_string(), "27".to_string(), "-v".to_string()])
+        .unwrap();
+
+    assert_eq!(seen, ("parth".to_string(), 27, true));
+}
+
+#[test]
+fn error_kinds() {
+    let mut edit = Command::name("lockbook").subcommand(
+        Command::name("edit")
+            .input(Flag::<String>::new("editor"))
+            .input(Arg::<i32>::name("line"))
+            .handler(|_, _| Ok(())),
+    );
+
+    let err = edit
+        .parse_args(&["edit".to_string(), "--force".to_string()])
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnknownFlag);
+    assert_eq!(err.token.as_deref(), Some("--force"));
+    assert_eq!(err.cmd_path.as_deref(), Some("lockbook edit"));
+
+    let err = edit
+        .parse_args(&["edit".to_string(), "four".to_string()])
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::InvalidValue);
+    assert_eq!(err.token.as_deref(), Some("four"));
+    assert_eq!(err.input.as_deref(), Some("line"));
+
+    let err = edit
+        .parse_args(&["edit".to_string(), "--editor".to_string()])
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::MissingValue);
+    assert_eq!(err.input.as_deref(), Some("editor"));
+
+    let err = edit.parse_args(&["rm".to_string()]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnknownSubcommand);
+    assert_eq!(err.cmd_path.as_deref(), Some("lockbook"));
+
+    let err = edit.parse_args(&[]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::MissingSubcommand);
+}
+
+#[test]
+fn missing_argument_kind() {
+    let err = Command::name("rm")
+        .input(Flag::bool("force"))
+        .input(Arg::<String>::name("target"))
+        .handler(|_, _| Ok(()))
+        .parse_args(&["-f".to_string()])
+        .unwrap_err();
+
+    assert_eq!(err.kind, ErrorKind::MissingArgument);
+    assert_eq!(err.input.as_deref(), Some("target"));
+
+    // run bare the error is the same, with help after it
+    let err = Command::name("rm")
+        .input(Flag::bool("force"))
+        .input(Arg::<String>::name("target"))
+        .handler(|_, _| Ok(()))
+        .parse_args(&[])
+        .unwrap_err();
+
+    assert_eq!(err.kind, ErrorKind::MissingArgument);
+    assert_eq!(err.input.as_deref(), Some("target"));
+    assert!(err.msg.starts_with("Missing required argument: target\n\n"));
+    assert!(err.msg.contains("--force"));
+}
+
+#[test]
+fn help_and_version_requested() {
+    let mut cmd = Command::name("lockbook")
+        .version("0.9.0")
+        .input(Flag::bool("force"))
+        .handler(|_| Ok(()));
+
+    let err = cmd.parse_args(&["--help".to_string()]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::HelpRequested);
+    assert_eq!(err.status, 0);
+    assert_eq!(err.msg, cmd.gen_help());
+
+    let err = cmd.parse_args(&["--version".to_string()]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::VersionRequested);
+    assert_eq!(err.status, 0);
+    assert_eq!(err.msg, "lockbook -- 0.9.0");
+}
+
+#[test]
+fn handler_error_kind() {
+    let err = Command::name("sync")
+        .handler(|| Err("server unreachable".into()))
+        .parse_args(&[])
+        .unwrap_err();
+
+    assert_eq!(err.kind, ErrorKind::Handler);
+    assert_eq!(err.msg, "server unreachable");
+    assert_eq!(err.cmd_path.as_deref(), Some("sync"));
+}
+
+#[test]
+fn suggest_subcommand() {
+    let err = Command::name("lockbook")
+        .subcommand(Command::name("edit").handler(|| Ok(())))
+        .subcommand(Command::name("sync").handler(|| Ok(())))
+        .parse_args(&["eidt".to_string()])
+        .unwrap_err();
+
+    assert!(err.msg.contains("did you mean `edit`?"));
+    assert!(err.msg.contains("subcommands: edit, sync"));
+
+    let err = Command::name("lockbook")
+        .subcommand(Command::name("edit").handler(|| Ok(())))
+        .parse_args(&["export".to_string()])
+        .unwrap_err();
+
+    assert!(!err.msg.contains("did you mean"));
+}
+
+#[test]
+fn suggest_flag() {
+    let err = Command::name("edit")
+        .input(Flag::<String>::new("editor"))
+        .input(Flag::bool("force"))
+        .handler(|_, _| Ok(()))
+        .parse_args(&["--edtor=vim".to_string()])
+        .unwrap_err();
+
+    assert!(err.msg.contains("did you mean `--editor`?"));
+}
+
+#[test]
+fn end_of_options() {
+    let mut target = String::new();
+    let mut force = false;
+
+    Command::name("rm")
+        .input(Flag::bool("force"))
+        .input(Arg::<String>::name("target"))
+        .handler(|f, t| {
+            force = f.get();
+            target = t.get();
+            Ok(())
+        })
+        .parse_args(&["-f".to_string(), "--".to_string(), "-file.md".to_string()])
+        .unwrap();
+
+    assert!(force);
+    assert_eq!(target, "-file.md");
+}
+
+#[test]
+fn trailing_args() {
+    let mut forwarded = vec![];
+
+    Command::name("lockbook")
+        .subcommand(
+            Command::name("exec")
+                .input(Flag::bool("verbose"))
+                .input(TrailingArgs::name("command"))
+                .handler(|verbose, command| {
+                    assert!(verbose.get());
+                    forwarded = command.get();
+                    Ok(())
+                }),
+        )
+        .parse_args(&tokens(&[
+            "exec", "-v", "--", "git", "status", "--short", "--",
+        ]))
+        .unwrap();
+
+    assert_eq!(forwarded, vec!["git", "status", "--short", "--"]);
+}
+
+#[test]
+fn trailing_args_without_separator() {
+    let mut forwarded = vec![];
+
+    Command::name("exec")
+        .input(Flag::bool("verbose"))
+        .input(TrailingArgs::name("command"))
+        .handler(|verbose, command| {
+            assert!(!verbose.get());
+            forwarded = command.get();
+            Ok(())
+        })
+        .parse_args(&tokens(&["ls", "-v"]))
+        .unwrap();
+
+    assert_eq!(forwarded, vec!["ls", "-v"]);
+}
+
+#[test]
+fn complete_trailing_args() {
+    let mut exec = Command::name("exec")
+        .input(Flag::bool("verbose"))
+        .input(TrailingArgs::name("command").completor(|prompt| Ok(vec![format!("{prompt}!")])))
+        .handler(|_, _| Ok(()));
+
+    let completions = exec
+        .complete_args(&["git".to_string(), "--st".to_string()])
+        .unwrap();
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].name, "--st!");
+
+    let completions = exec.complete_args(&["--v".to_string()]).unwrap();
+    assert_eq!(completions[0].name, "--verbose");
+
+    assert!(exec.gen_help().contains("[-- <command>...]"));
+}
+
+#[test]
+fn negative_number_args() {
+    let mut seen = (0, 0.0);
+
+    Command::name("seek")
+        .input(Flag::bool("relative"))
+        .input(Arg::<i32>::name("lines"))
+        .input(Arg::<f64>::name("offset"))
+        .handler(|relative, lines, offset| {
+            assert!(relative.get());
+            seen = (lines.get(), offset.get());
+            Ok(())
+        })
+        .parse_args(&tokens(&["-12", "-r", "-0.5"]))
+        .unwrap();
+
+    assert_eq!(seen, (-12, -0.5));
+
+    let err = Command::name("seek")
+        .input(Arg::<u32>::name("lines"))
+        .handler(|_| Ok(()))
+        .parse_args(&["-12".to_string()])
+        .unwrap_err();
+
+    assert_eq!(err.kind, ErrorKind::UnknownFlag);
+}
+
+#[test]
+fn negative_number_partly_matching_shorts() {
+    let mut seen = None;
+
+    Command::name("seek")
+        .input(Flag::bool("one").short('1'))
+        .input(Arg::<i32>::name("lines"))
+        .handler(|one, lines| {
+            seen = Some((one.get(), lines.get()));
+            Ok(())
+        })
+        .parse_args(&["-15".to_string()])
+        .unwrap();
+
+    assert_eq!(seen, Some((false, -15)));
+}
+
+#[test]
+fn negative_number_failing_validator() {
+    let err = Command::name("seek")
+        .input(Arg::<i32>::name("x").range(0..10))
+        .handler(|_| Ok(()))
+        .parse_args(&tokens(&["-5"]))
+        .unwrap_err();
+
+    assert_eq!(err.kind, ErrorKind::InvalidValue);
+    assert_eq!(err.input.as_deref(), Some("x"));
+    assert_eq!(err.msg, "-5 is not valid for x, expected 0..10");
+}
+
+#[test]
+fn allow_negative_numbers() {
+    let mut seen = (false, 0);
+
+    Command::name("seek")
+        .input(Flag::bool("1line"))
+        .input(Arg::<i32>::name("lines").default(0))
+        .handler(|one, lines| {
+            seen = (one.get(), lines.get());
+            Ok(())
+        })
+        .parse_args(&["-1".to_string()])
+        .unwrap();
+
+    assert_eq!(seen, (true, 0));
+
+    let mut seek = Command::name("seek")
+        .allow_negative_numbers()
+        .input(Flag::bool("1line"))
+        .input(Arg::<i32>::name("lines").default(0))
+        .handler(|one, lines| {
+            seen = (one.get(), lines.get());
+            Ok(())
+        });
+
+    seek.parse_args(&["-1".to_string()]).unwrap();
+    assert!(seek
+        .gen_help()
+        .contains("negative numbers such as -5 are read as args"));
+    drop(seek);
+
+    assert_eq!(seen, (false, -1));
+}
+
+#[test]
+fn complete_negative_number() {
+    let completions = Command::name("seek")
+        .input(Flag::bool("relative"))
+        .input(Arg::<i32>::name("lines"))
+        .input(Arg::<String>::name("unit").completor(|prompt| {
+            Ok(["lines", "pages"]
+                .into_iter()
+                .filter(|unit| unit.starts_with(prompt))
+                .map(String::from)
+                .collect())
+        }))
+        .handler(|_, _, _| Ok(()))
+        .complete_args(&["-5".to_string(), "p".to_string()])
+        .unwrap();
+
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].name, "pages");
+}
+
+#[test]
+fn negated_bool_flag() {
+    let color = |args: &[&str]| {
+        let mut color = None;
+        Command::name("ls")
+            .input(Flag::bool("color"))
+            .handler(|flag| {
+                color = flag.get_opt();
+                Ok(())
+            })
+            .parse_args(&tokens(args))
+            .unwrap();
+        color
+    };
+
+    assert_eq!(color(&["--color"]), Some(true));
+    assert_eq!(color(&["--no-color"]), Some(false));
+    assert_eq!(color(&[]), None);
+}
+
+#[test]
+fn complete_negated_flags() {
+    let mut ls = Command::name("ls")
+        .input(Flag::bool("color"))
+        .input(Flag::<String>::new("sort"))
+        .handler(|_, _| Ok(()));
+
+    let completions = ls.complete_args(&["--".to_string()]).unwrap();
+    let names = completions
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["--color", "--no-color", "--sort=", "--help"]);
+
+    let completions = ls.complete_args(&["--no".to_string()]).unwrap();
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].name, "--no-color");
+}
+
+#[test]
+fn list_flag() {
+    let mut tags = vec![];
+
+    Command::name("tag")
+        .input(ListFlag::<String>::new("tag"))
+        .input(Arg::<String>::name("target"))
+        .handler(|tag, _| {
+            tags = tag.get();
+            Ok(())
+        })
+        .parse_args(&tokens(&[
+            "--tag",
+            "work",
+            "todo.md",
+            "-t=urgent,later",
+            "--tag=home",
+        ]))
+        .unwrap();
+
+    assert_eq!(tags, vec!["work", "urgent", "later", "home"]);
+}
+
+#[test]
+fn count_flag() {
+    let verbosity = |args: &[&str]| {
+        let mut verbosity = 0;
+        Command::name("sync")
+            .input(CountFlag::new("verbose"))
+            .input(Flag::bool("force"))
+            .handler(|verbose, _| {
+                verbosity = verbose.get();
+                Ok(())
+            })
+            .parse_args(&tokens(args))
+            .unwrap();
+        verbosity
+    };
+
+    assert_eq!(verbosity(&[]), 0);
+    assert_eq!(verbosity(&["-vvfv"]), 3);
+    assert_eq!(verbosity(&["--verbose", "-f", "--verbose"]), 2);
+}
+
+#[test]
+fn complete_used_flags() {
+    let mut sync = Command::name("sync")
+        .input(CountFlag::new("verbose"))
+        .input(ListFlag::<String>::new("tag"))
+        .input(Flag::bool("force"))
+        .handler(|_, _, _| Ok(()));
+
+    let completions = sync
+        .complete_args(&tokens(&["-vf", "--tag", "work", "--"]))
+        .unwrap();
+    let names = completions
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>();
+
+    assert_eq!(names, vec!["--verbose", "--tag=", "--help"]);
+}
+
+#[test]
+fn map_flag() {
+    let mut defines = HashMap::new();
+
+    Command::name("render")
+        .input(MapFlag::<String, u16>::new("define").short('D'))
+        .handler(|define| {
+            defines = define.get();
+            Ok(())
+        })
+        .parse_args(&tokens(&[
+            "-D",
+            "width=80",
+            "--define=height=24",
+            "-D",
+            "width=100",
+        ]))
+        .unwrap();
+
+    assert_eq!(defines.len(), 2);
+    assert_eq!(defines["width"], 100);
+    assert_eq!(defines["height"], 24);
+}
+
+#[test]
+fn map_flag_malformed_pair() {
+    let mut render = Command::name("render")
+        .input(MapFlag::<String, u16>::new("define").short('D'))
+        .handler(|_| Ok(()));
+
+    let err = render
+        .parse_args(&["-D".to_string(), "width".to_string()])
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::InvalidValue);
+    assert_eq!(err.token.as_deref(), Some("width"));
+
+    let err = render
+        .parse_args(&["--define=width=wide".to_string()])
+        .unwrap_err();
+    assert_eq!(err.token.as_deref(), Some("width=wide"));
+    assert!(err
+        .msg
+        .contains("wide cannot be parsed as a value for define"));
+}
+
+#[test]
+fn complete_map_flag() {
+    let mut config = Command::name("config")
+        .input(
+            MapFlag::<String, String>::new("set")
+                .key_completor(|prompt| {
+                    Ok(["editor", "theme"]
+                        .into_iter()
+                        .filter(|key| key.starts_with(prompt))
+                        .map(String::from)
+                        .collect())
+                })
+                .value_completor(|key, prompt| {
+                    let values = match key {
+                        "theme" => vec!["dark", "light"],
+                        _ => vec![],
+                    };
+                    Ok(values
+                        .into_iter()
+                        .filter(|value| value.starts_with(prompt))
+                        .map(String::from)
+                        .collect())
+                }),
+        )
+        .handler(|_| Ok(()));
+
+    let completions = config
+        .complete_args(&["--set".to_string(), "th".to_string()])
+        .unwrap();
+    assert_eq!(completions[0].name, "theme=");
+
+    let completions = config
+        .complete_args(&["--set=theme=d".to_string()])
+        .unwrap();
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].name, "--set=theme=dark");
+}
+
+#[test]
+fn flag_defaults() {
+    let mut seen = None;
+
+    let editor = std::cell::Cell::new("vi");
+    let mut edit = Command::name("edit")
+        .input(
+            Flag::<String>::new("editor")
+                .default_with(|| editor.get().to_string())
+                .show_default(),
+        )
+        .input(
+            Flag::name("host")
+                .default(std::net::Ipv4Addr::LOCALHOST)
+                .show_default(),
+        )
+        .input(Flag::name("type").default(FileType::Document))
+        .input(Flag::bool("force"))
+        .handler(|editor, host, file_type, force| {
+            seen = Some((editor.get(), host.get(), file_type.get(), force.get()));
+            Ok(())
+        });
+
+    let help = edit.gen_help();
+    assert!(help.contains("[default: vi]"));
+    assert!(help.contains("[default: 127.0.0.1]"));
+    assert_eq!(help.matches("[default:").count(), 2);
+
+    editor.set("nano");
+    edit.parse_args(&["--host=10.0.0.1".to_string()]).unwrap();
+    drop(edit);
+
+    assert_eq!(
+        seen,
+        Some((
+            "nano".to_string(),
+            "10.0.0.1".parse().unwrap(),
+            FileType::Document,
+            false
+        ))
+    );
+}
+
+#[test]
+fn arg_default_with() {
+    let mut target = String::new();
+
+    let mut ls = Command::name("ls")
+        .input(
+            Arg::<String>::name("folder")
+                .default_with(|| "/".to_string())
+                .show_default(),
+        )
+        .handler(|folder| {
+            target = folder.get();
+            Ok(())
+        });
+
+    assert!(ls.gen_help().contains("[default: /]"));
+    ls.parse_args(&[]).unwrap();
+    drop(ls);
+
+    assert_eq!(target, "/");
+}
+
+#[test]
+fn arg_try_get_and_take() {
+    let mut seen = None;
+
+    Command::name("cat")
+        .input(Arg::<String>::name("file"))
+        .input(ListArg::<String>::name("more"))
+        .handler(|file, more| {
+            assert_eq!(file.try_get().as_deref(), Some("a.md"));
+            seen = Some((file.take(), more.take()));
+            assert_eq!(file.try_get(), None);
+            assert!(more.get().is_empty());
+            Ok(())
+        })
+        .parse_args(&tokens(&["a.md", "b.md"]))
+        .unwrap();
+
+    assert_eq!(seen, Some(("a.md".to_string(), vec!["b.md".to_string()])));
+    assert_eq!(Arg::<String>::name("file").try_get(), None);
+}
+
+#[test]
+fn flag_without_default_is_optional() {
+    let mut seen = None;
+    Command::name("serve")
+        .input(Flag::<std::net::Ipv4Addr>::name("host"))
+        .input(Flag::bool("verbose"))
+        .handler(|host, verbose| {
+            seen = Some((host.get_opt(), verbose.get()));
+            Ok(())
+        })
+        .parse_args(&tokens(&["-v"]))
+        .unwrap();
+
+    assert_eq!(seen, Some((None, true)));
+
+    let err = Command::name("serve")
+        .input(Flag::<std::net::Ipv4Addr>::name("host").required())
+        .input(Flag::bool("verbose"))
+        .handler(|_, _| unreachable!())
+        .parse_args(&tokens(&["-v"]))
+        .unwrap_err();
+
+    assert_eq!(err.kind, ErrorKind::MissingArgument);
+    assert_eq!(err.input.as_deref(), Some("host"));
+    assert_eq!(err.msg, "Missing required flag --host");
+}
+
+#[test]
+fn custom_parser() {
+    let open = |args: &[&str]| -> CliResult<u64> {
+        let mut seen = None;
+        Command::name("open")
+            .input(Arg::<u64>::name("target").parser(|target| {
+                match target {
+                    "todo.md" => Ok(7),
+                    _ => target
+                        .parse()
+                        .map_err(|_| format!("no document at {target}").into()),
+                }
+            }))
+            .input(
+                Flag::with_parser("at", |at| match at {
+                    "top" => Ok(0usize),
+                    _ => Err("--at is top".into()),
+                })
+                .default(0),
+            )
+            .handler(|target, _| {
+                seen = Some(target.get());
+                Ok(())
+            })
+            .parse_args(&tokens(args))?;
+        Ok(seen.unwrap())
+    };
+
+    assert_eq!(open(&["todo.md"]), Ok(7));
+    assert_eq!(open(&["12", "--at=top"]), Ok(12));
+
+    let err = open(&["notes.md"]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::InvalidValue);
+    assert_eq!(err.msg, "no document at notes.md");
+    assert_eq!(err.token.as_deref(), Some("notes.md"));
+    assert_eq!(err.input.as_deref(), Some("target"));
+
+    let err = open(&["12", "--at=bottom"]).unwrap_err();
+    assert_eq!(err.msg, "--at is top");
+}
+
+#[test]
+fn parse_error_source() {
+    let err = Command::name("age")
+        .input(Arg::i32("age"))
+        .handler(|_| Ok(()))
+        .parse_args(&tokens(&["abc"]))
+        .unwrap_err();
+
+    assert_eq!(
+        err.msg,
+        "abc cannot be parsed for age: invalid digit found in string"
+    );
+    let source = err.source.unwrap();
+    assert!(source.downcast_ref::<std::num::ParseIntError>().is_some());
+    assert_eq!(source.to_string(), "invalid digit found in string");
+
+    let err = Command::name("sync")
+        .input(Flag::bool("force"))
+        .handler(|_| Ok(()))
+        .parse_args(&tokens(&["--force=maybe"]))
+        .unwrap_err();
+
+    assert!(err
+        .source
+        .unwrap()
+        .downcast_ref::<std::str::ParseBoolError>()
+        .is_some());
+
+    // other types only say why once asked to, their errors needn't be `Display`
+    let err = Command::name("create")
+        .input(Flag::<FileType>::new("type"))
+        .handler(|_| Ok(()))
+        .parse_args(&tokens(&["--type=link"]))
+        .unwrap_err();
+
+    assert_eq!(err.msg, "link cannot be parsed for type");
+    assert_eq!(err.source, None);
+
+    let err = Command::name("render")
+        .input(MapFlag::<String, u8>::new("define"))
+        .handler(|_| Ok(()))
+        .parse_args(&tokens(&["--define=width=300"]))
+        .unwrap_err();
+
+    assert_eq!(
+        err.msg,
+        "300 cannot be parsed as a value for define in width=300"
+    );
+    assert_eq!(err.source, None);
+
+    let err = Command::name("render")
+        .input(MapFlag::<String, u8>::new("define").parse_details())
+        .handler(|_| Ok(()))
+        .parse_args(&tokens(&["--define=width=300"]))
+        .unwrap_err();
+
+    assert_eq!(
+        err.msg,
+        "300 cannot be parsed as a value for define in width=300: number too large to fit in target type"
+    );
+    assert!(err.source.is_some());
+}
+
+#[test]
+fn parse_details_on_every_input() {
+    let source = |result: CliResult<()>| result.unwrap_err().source.map(|s| s.to_string());
+    let digits = Some("invalid digit found in string".to_string());
+
+    let opt = Command::name("seek")
+        .input(OptArg::<u8>::name("lines").parse_details())
+        .handler(|_| Ok(()))
+        .parse_args(&tokens(&["x"]));
+    assert_eq!(source(opt), digits);
+
+    let list = Command::name("sum")
+        .input(ListArg::<u8>::name("numbers").parse_details())
+        .handler(|_| Ok(()))
+        .parse_args(&tokens(&["1", "x"]));
+    assert_eq!(source(list), digits);
+
+    let flag = Command::name("sum")
+        .input(ListFlag::<u8>::new("add").parse_details())
+        .handler(|_| Ok(()))
+        .parse_args(&tokens(&["--add=1,x"]));
+    assert_eq!(source(flag), digits);
+
+    let flag = Command::name("sum")
+        .input(Flag::<u8>::new("add").parse_details())
+        .handler(|_| Ok(()))
+        .parse_args(&tokens(&["--add=x"]));
+    assert_eq!(source(flag), digits);
+}
+
+#[test]
+fn validators() {
+    let serve = |args: &[&str]| -> CliResult<(u16, String)> {
+        let mut seen = None;
+        Command::name("serve")
+            .input(Arg::<u16>::name("port").range(1..=65535))
+            .input(
+                Flag::<String>::new("user")
+                    .pattern(r"^[a-z_][a-z0-9_-]{0,31}$")
+                    .default("root".to_string()),
+            )
+            .input(
+                Flag::<String>::new("root")
+                    .path_exists()
+                    .default(".".to_string()),
+            )
+            .input(
+                Flag::<u32>::new("workers")
+                    .validate("an even number", |workers| workers % 2 == 0)
+                    .default(2),
+            )
+            .handler(|port, user, _, _| {
+                seen = Some((port.get(), user.get()));
+                Ok(())
+            })
+            .parse_args(&tokens(args))?;
+        Ok(seen.unwrap())
+    };
+
+    assert_eq!(
+        serve(&["8080", "--user=www-data"]),
+        Ok((8080, "www-data".to_string()))
+    );
+
+    let err = serve(&["0"]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::InvalidValue);
+    assert_eq!(err.msg, "0 is not valid for port, expected 1..=65535");
+    assert_eq!(err.input.as_deref(), Some("port"));
+
+    let err = serve(&["80", "--user=Admin"]).unwrap_err();
+    assert_eq!(
+        err.msg,
+        "Admin is not valid for user, expected matching ^[a-z_][a-z0-9_-]{0,31}$"
+    );
+
+    let err = serve(&["80", "--root=/does/not/exist"]).unwrap_err();
+    assert_eq!(
+        err.msg,
+        "/does/not/exist is not valid for root, expected an existing path"
+    );
+
+    let err = serve(&["80", "--workers", "3"]).unwrap_err();
+    assert_eq!(
+        err.msg,
+        "3 is not valid for workers, expected an even number"
+    );
+    assert_eq!(err.token.as_deref(), Some("3"));
+}
+
+#[test]
+fn validators_in_help() {
+    let help = Command::name("serve")
+        .input(Arg::<u16>::name("port").range(1..=65535))
+        .input(Flag::<String>::new("user").pattern("^[a-z]+$"))
+        .handler(|_, _| Ok(()))
+        .gen_help();
+
+    assert!(help.contains("<port: 1..=65535>"));
+    assert!(help.contains("[expected: matching ^[a-z]+$]"));
+}
+
+#[test]
+#[should_panic(expected = "invalid pattern ^(foo|bar)$")]
+fn unsupported_pattern() {
+    Flag::<String>::new("name").pattern("^(foo|bar)$");
+}
+
+#[test]
+fn escaped_pattern() {
+    let check = |value: &str| {
+        Command::name("tag")
+            .input(Arg::<String>::name("tag").pattern(r"^v\(\d+\)\|$"))
+            .handler(|_| Ok(()))
+            .parse_args(&[value.to_string()])
+    };
+
+    assert!(check("v(12)|").is_ok());
+    assert!(check("v12").is_err());
+}
+
+#[derive(ValueEnum, Clone, Debug, PartialEq)]
+#[value(display)]
+enum Shell {
+    Bash,
+    /// the friendly interactive shell
+    Fish,
+    #[value(alias = "z")]
+    Zsh,
+}
+
+#[test]
+fn value_enum_arg() {
+    let init = |args: &[&str]| -> CliResult<Shell> {
+        let mut seen = None;
+        Command::name("init")
+            .input(Arg::<Shell>::value_enum("shell"))
+            .input(Flag::<Shell>::value_enum("login").default(Shell::Bash))
+            .handler(|shell, _| {
+                seen = Some(shell.get());
+                Ok(())
+            })
+            .parse_args(&tokens(args))?;
+        Ok(seen.unwrap())
+    };
+
+    assert_eq!(init(&["fish"]), Ok(Shell::Fish));
+    assert_eq!(init(&["Z", "--login", "zsh"]), Ok(Shell::Zsh));
+
+    let err = init(&["tcsh"]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::InvalidValue);
+    assert_eq!(
+        err.msg,
+        "tcsh cannot be parsed for shell: expected one of bash, fish, zsh"
+    );
+}
+
+#[test]
+fn value_enum_completions_and_help() {
+    let mut init = Command::name("init")
+        .input(Arg::<Shell>::value_enum("shell"))
+        .input(
+            Flag::<Shell>::value_enum("login")
+                .default(Shell::Bash)
+                .show_default(),
+        )
+        .handler(|_, _| Ok(()));
+
+    let completions = init.complete_args(&["f".to_string()]).unwrap();
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].name, "fish");
+    assert_eq!(
+        completions[0].desc.as_deref(),
+        Some("the friendly interactive shell")
+    );
+
+    let completions = init.complete_args(&["Z".to_string()]).unwrap();
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].name, "zsh");
+
+    let completions = init
+        .complete_args(&["fish".to_string(), "--login".to_string(), "".to_string()])
+        .unwrap();
+    let names: Vec<_> = completions.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["bash", "fish", "zsh"]);
+
+    let help = init.gen_help();
+    assert_eq!(
+        help.matches("[possible values: bash, fish, zsh]").count(),
+        2
+    );
+    assert!(help.contains("[default: bash]"));
+}
+
+#[test]
+fn conflicting_flags() {
+    let share = |args: &[&str]| {
+        Command::name("share")
+            .input(Flag::bool("public"))
+            .input(Flag::bool("private").no_short())
+            .input(Arg::<String>::name("target"))
+            .conflicts("public", "private")
+            .handler(|_, _, _| Ok(()))
+            .parse_args(&tokens(args))
+    };
+
+    assert_eq!(share(&["--public", "a.md"]), Ok(()));
+    assert_eq!(share(&["a.md"]), Ok(()));
+
+    let err = share(&["--private", "a.md", "--public"]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::ArgumentConflict);
+    assert_eq!(err.msg, "--public cannot be used with --private");
+    assert_eq!(err.input.as_deref(), Some("public"));
+}
+
+#[test]
+fn required_flags() {
+    let grant = |args: &[&str]| {
+        Command::name("grant")
+            .input(Flag::<String>::new("user"))
+            .input(Flag::<String>::new("role"))
+            .requires("user", "role")
+            .handler(|_, _| Ok(()))
+            .parse_args(&tokens(args))
+    };
+
+    assert_eq!(grant(&["--user=parth", "--role=admin"]), Ok(()));
+    assert_eq!(grant(&["--role=admin"]), Ok(()));
+
+    let err = grant(&["--user=parth"]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::MissingArgument);
+    assert_eq!(err.msg, "--user requires --role");
+    assert_eq!(err.input.as_deref(), Some("role"));
+}
+
+#[test]
+fn exactly_one_of() {
+    let export = |args: &[&str]| {
+        Command::name("export")
+            .input(Flag::bool("json"))
+            .input(Flag::bool("yaml"))
+            .input(OptArg::<String>::name("target"))
+            .exactly_one_of(&["json", "yaml", "target"])
+            .handler(|_, _, _| Ok(()))
+            .parse_args(&tokens(args))
+    };
+
+    assert_eq!(export(&["--yaml"]), Ok(()));
+
+    let err = export(&[]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::MissingArgument);
+    assert_eq!(err.msg, "one of --json, --yaml, <target> is required");
+
+    let err = export(&["--json", "a.md"]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::ArgumentConflict);
+    assert_eq!(err.msg, "--json cannot be used with <target>");
+}
+
+#[test]
+fn rules_in_help_and_completions() {
+    let mut share = Command::name("share")
+        .input(Flag::bool("public"))
+        .input(Flag::bool("private").no_short())
+        .input(Flag::<String>::new("user"))
+        .input(Flag::<String>::new("role"))
+        .conflicts("public", "private")
+        .requires("user", "role")
+        .handler(|_, _, _, _| Ok(()));
+
+    let help = share.gen_help();
+    assert!(help.contains("RULES:"));
+    assert!(help.contains("--public cannot be used with --private"));
+    assert!(help.contains("--user requires --role"));
+
+    let completions = share
+        .complete_args(&["--public".to_string(), "--p".to_string()])
+        .unwrap();
+    assert!(completions.is_empty());
+
+    let completions = share
+        .complete_args(&["--user=parth".to_string(), "--".to_string()])
+        .unwrap();
+    let names: Vec<_> = completions.iter().map(|c| c.name.as_str()).collect();
+    assert!(names.contains(&"--public") && names.contains(&"--private"));
+    assert!(names.contains(&"--role="));
+}
+
+#[test]
+#[should_panic(expected = "share: a rule names secret, which isn't one of its inputs")]
+fn rule_naming_unknown_input() {
+    let _ = Command::name("share")
+        .input(Flag::bool("public"))
+        .conflicts("public", "secret")
+        .handler(|_| Ok(()))
+        .parse_args(&tokens(&["--public"]));
+}
+
+#[test]
+#[should_panic(expected = "share: a rule names secret, which isn't one of its inputs")]
+fn rule_after_handler_naming_unknown_input() {
+    let _ = Command::name("share")
+        .input(Flag::bool("public"))
+        .handler(|_| Ok(()))
+        .requires("public", "secret")
+        .parse_args(&[]);
+}
+
+#[test]
+fn rules_on_globals() {
+    let lockbook = |args: &[&str]| {
+        let verbose = Global::new(Flag::bool("verbose"));
+        let quiet = Global::new(Flag::bool("quiet"));
+        Command::name("lb")
+            .global(&verbose)
+            .global(&quiet)
+            .conflicts("verbose", "quiet")
+            .subcommand(Command::name("sync").handler(|| Ok(())))
+            .subcommand(
+                Command::name("edit")
+                    .input(Flag::bool("force"))
+                    .requires("force", "verbose")
+                    .handler(|_| Ok(())),
+            )
+            .parse_args(&tokens(args))
+    };
+
+    assert_eq!(lockbook(&["--verbose", "sync"]), Ok(()));
+    assert_eq!(lockbook(&["edit", "--force", "--verbose"]), Ok(()));
+
+    let err = lockbook(&["--verbose", "--quiet", "sync"]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::ArgumentConflict);
+    assert_eq!(err.msg, "--verbose cannot be used with --quiet");
+
+    let err = lockbook(&["edit", "--force"]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::MissingArgument);
+    assert_eq!(err.msg, "--force requires --verbose");
+}
+
+#[test]
+#[should_panic(expected = "lb sync: a rule names quiet, which isn't one of its inputs")]
+fn rule_naming_unknown_global() {
+    let _ = Command::name("lb")
+        .global(&Global::new(Flag::bool("verbose")))
+        .conflicts("verbose", "quiet")
+        .subcommand(Command::name("sync").handler(|| Ok(())))
+        .parse_args(&tokens(&["sync"]));
+}
+
+#[test]
+fn required_flag() {
+    let deploy = |args: &[&str]| -> CliResult<String> {
+        let mut seen = None;
+        Command::name("deploy")
+            .input(Flag::<String>::new("env").required())
+            .input(Flag::bool("dry-run"))
+            .handler(|env, _| {
+                seen = Some(env.get());
+                Ok(())
+            })
+            .parse_args(&tokens(args))?;
+        Ok(seen.unwrap())
+    };
+
+    assert_eq!(deploy(&["--env=prod"]), Ok("prod".to_string()));
+
+    let err = deploy(&["--dry-run"]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::MissingArgument);
+    assert_eq!(err.msg, "Missing required flag --env");
+    assert_eq!(err.input.as_deref(), Some("env"));
+}
+
+#[test]
+fn required_flag_help_and_completions() {
+    let mut deploy = Command::name("deploy")
+        .input(Flag::bool("dry-run"))
+        .input(Flag::<String>::new("env").required())
+        .handler(|_, _| Ok(()));
+
+    let help = deploy.gen_help();
+    assert!(help.contains("deploy --env <env> [options]"));
+    assert_eq!(help.matches("[required]").count(), 1);
+
+    let completions = deploy.complete_args(&["--".to_string()]).unwrap();
+    let names: Vec<_> = completions.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["--env=", "--dry-run", "--no-dry-run", "--help"]);
+}
+
+#[test]
+fn subcommand_aliases() {
+    let lockbook = |args: &[&str]| -> CliResult<&'static str> {
+        let seen = std::cell::Cell::new(None);
+        Command::name("lockbook")
+            .subcommand(
+                Command::name("delete")
+                    .alias("rm")
+                    .input(Arg::<String>::name("target"))
+                    .handler(|_| {
+                        seen.set(Some("delete"));
+                        Ok(())
+                    }),
+            )
+            .subcommand(Command::name("list").alias("ls").handler(|| {
+                seen.set(Some("list"));
+                Ok(())
+            }))
+            .parse_args(&tokens(args))?;
+        Ok(seen.get().unwrap())
+    };
+
+    assert_eq!(lockbook(&["rm", "a.md"]), Ok("delete"));
+    assert_eq!(lockbook(&["delete", "a.md"]), Ok("delete"));
+    assert_eq!(lockbook(&["ls"]), Ok("list"));
+
+    let err = lockbook(&["rm"]).unwrap_err();
+    assert_eq!(err.cmd_path.as_deref(), Some("lockbook delete"));
+}
+
+#[test]
+fn subcommand_aliases_in_help_and_completions() {
+    let mut lockbook = Command::name("lockbook")
+        .subcommand(
+            Command::name("delete")
+                .alias("rm")
+                .alias("del")
+                .handler(|| Ok(())),
+        )
+        .subcommand(Command::name("debug").hidden().handler(|| Ok(())));
+
+    let help = lockbook.gen_help();
+    assert!(help.contains("delete (rm, del)"));
+    assert!(!help.contains("debug"));
+
+    let completions = lockbook.complete_args(&["d".to_string()]).unwrap();
+    let names: Vec<_> = completions.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["delete"]);
+    assert!(lockbook
+        .complete_args(&["r".to_string()])
+        .unwrap()
+        .is_empty());
+
+    let err = lockbook.parse_args(&["nope".to_string()]).unwrap_err();
+    assert!(err.msg.ends_with("subcommands: delete"));
+}
+
+#[test]
+fn hidden_subcommands_and_flags() {
+    let mut seen = None;
+    Command::name("lockbook")
+        .subcommand(
+            Command::name("debug")
+                .hidden()
+                .input(Flag::bool("trace").hidden())
+                .handler(|trace| {
+                    seen = Some(trace.get());
+                    Ok(())
+                }),
+        )
+        .parse_args(&["debug".to_string(), "--trace".to_string()])
+        .unwrap();
+    assert_eq!(seen, Some(true));
+
+    let mut sync = Command::name("sync")
+        .input(Flag::bool("trace").hidden())
+        .input(ListFlag::<String>::new("only").hidden())
+        .input(Flag::bool("force"))
+        .handler(|_, _, _| Ok(()));
+
+    let help = sync.gen_help();
+    assert!(!help.contains("trace") && !help.contains("only"));
+
+    let completions = sync.complete_args(&["--".to_string()]).unwrap();
+    let names: Vec<_> = completions.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["--force", "--no-force", "--help"]);
+
+    let err = sync.parse_args(&["--trac".to_string()]).unwrap_err();
+    assert!(!err.msg.contains("did you mean"));
+}
+
+#[test]
+fn infer_subcommand_prefixes() {
+    let lockbook = |args: &[&str]| -> CliResult<&'static str> {
+        let seen = std::cell::Cell::new(None);
+        Command::name("lockbook")
+            .infer_prefixes()
+            .subcommand(Command::name("sync").handler(|| {
+                seen.set(Some("sync"));
+                Ok(())
+            }))
+            .subcommand(Command::name("share").alias("send").handler(|| {
+                seen.set(Some("share"));
+                Ok(())
+            }))
+            .subcommand(Command::name("debug").hidden().handler(|| {
+                seen.set(Some("debug"));
+                Ok(())
+            }))
+            .parse_args(&tokens(args))?;
+        Ok(seen.get().unwrap())
+    };
+
+    assert_eq!(lockbook(&["sy"]), Ok("sync"));
+    assert_eq!(lockbook(&["sh"]), Ok("share"));
+    assert_eq!(lockbook(&["se"]), Ok("share"));
+    assert_eq!(lockbook(&["debug"]), Ok("debug"));
+    assert_eq!(
+        lockbook(&["de"]).unwrap_err().kind,
+        ErrorKind::UnknownSubcommand
+    );
+
+    let err = lockbook(&["s"]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::AmbiguousPrefix);
+    assert_eq!(err.msg, "s is ambiguous, it could be sync, share");
+    assert_eq!(err.token.as_deref(), Some("s"));
+}
+
+#[test]
+fn infer_flag_prefixes() {
+    let sync = |args: &[&str]| -> CliResult<(bool, u32)> {
+        let mut seen = None;
+        Command::name("lockbook")
+            .infer_prefixes()
+            .subcommand(
+                Command::name("sync")
+                    .input(Flag::bool("verbose"))
+                    .input(Flag::bool("version-check").no_short())
+                    .input(Flag::<u32>::new("retries"))
+                    .handler(|verbose, _, retries| {
+                        seen = Some((verbose.get(), retries.get()));
+                        Ok(())
+                    }),
+            )
+            .parse_args(&tokens(args))?;
+        Ok(seen.unwrap())
+    };
+
+    assert_eq!(sync(&["sync", "--verb", "--ret=3"]), Ok((true, 3)));
+    assert_eq!(sync(&["sync", "--re", "2"]), Ok((false, 2)));
+    assert_eq!(sync(&["sync", "--no-verb"]), Ok((false, 0)));
+
+    let err = sync(&["sync", "--ver"]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::AmbiguousPrefix);
+    assert_eq!(
+        err.msg,
+        "--ver is ambiguous, it could be --verbose, --version-check"
+    );
+    assert_eq!(err.cmd_path.as_deref(), Some("lockbook sync"));
+
+    let err = Command::name("sync")
+        .input(Flag::bool("verbose"))
+        .handler(|_| Ok(()))
+        .parse_args(&["--verb".to_string()])
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnknownFlag);
+}
+
+#[test]
+fn complete_inferred_prefixes() {
+    let mut lockbook = Command::name("lockbook").infer_prefixes().subcommand(
+        Command::name("sync")
+            .input(Flag::bool("verbose"))
+            .input(Flag::bool("force"))
+            .handler(|_, _| Ok(())),
+    );
+
+    let completions = lockbook
+        .complete_args(&["sy".to_string(), "--verb".to_string(), "--".to_string()])
+        .unwrap();
+    let names: Vec<_> = completions.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["--force", "--no-force", "--help"]);
+}